@@ -2,30 +2,27 @@
 
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, HashSet},
+    hash::Hash,
 };
 
-use crate::map::{MapData, MapPos, Tile};
-
-const NEIGHBORS: &[(isize, isize); 8] = &[
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, 1),
-    (1, 0),
-    (1, 1),
-];
+use crate::{
+    coord::Coord,
+    graph::{path_from, SearchSpace},
+    map::MapPos,
+    movement::StepCosts,
+    render::SearchTrace,
+    replay::Replay,
+};
 
 // this was taken form the BinaryHeap docs
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct State {
+struct State<N> {
     cost: isize,
-    pos: MapPos,
+    pos: N,
 }
 
-impl Ord for State {
+impl<N: Ord> Ord for State<N> {
     fn cmp(&self, other: &Self) -> Ordering {
         other
             .cost
@@ -35,27 +32,117 @@ impl Ord for State {
     }
 }
 
-impl PartialOrd for State {
+impl<N: Ord> PartialOrd for State<N> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-#[derive(Default)]
-pub struct AStar {
-    frontier: BinaryHeap<State>,
-    came_from: HashMap<MapPos, MapPos>,
-    cost_so_far: HashMap<MapPos, isize>,
+#[derive(Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Heuristic {
+    #[default]
+    Manhattan,
+    Chebyshev,
+    /// exact on an open grid when diagonals cost more than straight steps
+    Octile,
+    Euclidean,
+    /// no heuristic at all, which turns the search into dijkstra
+    Zero,
 }
 
-impl AStar {
-    pub fn run(
+impl Heuristic {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "manhattan" => Some(Heuristic::Manhattan),
+            "chebyshev" => Some(Heuristic::Chebyshev),
+            "octile" => Some(Heuristic::Octile),
+            "euclidean" => Some(Heuristic::Euclidean),
+            "zero" | "none" => Some(Heuristic::Zero),
+            _ => None,
+        }
+    }
+
+    /// the estimated cost from `a` to `b` in the units of `costs`
+    pub fn distance(self, a: Coord, b: Coord, costs: &StepCosts) -> isize {
+        let Coord { x: dx, y: dy } = (a - b).abs();
+
+        let straight = costs.straight;
+
+        match self {
+            Heuristic::Manhattan => straight * (dx + dy),
+            Heuristic::Chebyshev => straight * dx.max(dy),
+            Heuristic::Octile => {
+                straight * dx.max(dy) + (costs.diagonal - straight) * dx.min(dy)
+            }
+            Heuristic::Euclidean => {
+                (straight as f64 * ((dx * dx + dy * dy) as f64).sqrt()) as isize
+            }
+            Heuristic::Zero => 0,
+        }
+    }
+}
+
+/// a* over any search space, grids by default
+pub struct AStar<N = MapPos> {
+    frontier: BinaryHeap<State<N>>,
+    came_from: HashMap<N, N>,
+    cost_so_far: HashMap<N, isize>,
+    closed: HashSet<N>,
+    replay: Option<Replay<N>>,
+}
+
+impl<N: Copy + Eq + Hash + Ord> Default for AStar<N> {
+    fn default() -> Self {
+        Self {
+            frontier: BinaryHeap::new(),
+            came_from: HashMap::new(),
+            cost_so_far: HashMap::new(),
+            closed: HashSet::new(),
+            replay: None,
+        }
+    }
+}
+
+impl<N: Copy + Eq + Hash + Ord> AStar<N> {
+    /// an AStar that keeps a replay of every expansion it makes
+    pub fn recording() -> Self {
+        Self {
+            replay: Some(Replay::default()),
+            ..Self::default()
+        }
+    }
+
+    pub fn replay(&self) -> Option<&Replay<N>> {
+        self.replay.as_ref()
+    }
+
+    /// the cost of the best path found to `node` in the last run
+    pub fn cost(&self, node: N) -> Option<isize> {
+        self.cost_so_far.get(&node).copied()
+    }
+
+    /// the nodes the last run expanded
+    pub fn closed(&self) -> &HashSet<N> {
+        &self.closed
+    }
+
+    /// the cheapest path from `start` to `end`, end first, empty when there
+    /// is none
+    pub fn run<S: SearchSpace<Node = N>>(
         &mut self,
-        map_data: &MapData,
-        start: MapPos,
-        end: MapPos,
-    ) -> Vec<MapPos> {
-        let map_width = map_data.map_width;
+        space: &S,
+        start: N,
+        end: N,
+    ) -> Vec<N> {
+        self.frontier.clear();
+        self.came_from.clear();
+        self.cost_so_far.clear();
+        self.closed.clear();
+
+        if let Some(replay) = self.replay.as_mut() {
+            *replay = Replay::default();
+        }
 
         let start_state = State {
             cost: 0,
@@ -64,32 +151,24 @@ impl AStar {
 
         self.frontier.push(start_state);
 
-        // its more likely that 0 is a valid rather then usize::MAX
-        self.came_from.insert(start, usize::MAX);
-
         self.cost_so_far.insert(start, 0);
 
         while let Some(current) = self.frontier.pop() {
-            if current.pos == end {
-                break;
+            // a node is pushed again each time it gets cheaper, the older
+            // entries are left in the heap and skipped here
+            if !self.closed.insert(current.pos) {
+                continue;
             }
 
-            for (o_x, o_y) in NEIGHBORS {
-                let new_pos =
-                    offset_position(*o_x, *o_y, current.pos, map_width);
-
-                if new_pos < 0 || new_pos >= map_data.map.len() as isize {
-                    continue;
-                }
-
-                let new_pos = new_pos as MapPos;
+            if let Some(replay) = self.replay.as_mut() {
+                replay.expand(current.pos);
+            }
 
-                let tile_cost = if map_data.map[new_pos] == Tile::Wall {
-                    100
-                } else {
-                    1
-                };
+            if current.pos == end {
+                break;
+            }
 
+            for (new_pos, tile_cost) in space.neighbors(current.pos) {
                 let new_cost =
                     self.cost_so_far.get(&current.pos).unwrap() + tile_cost;
 
@@ -98,8 +177,12 @@ impl AStar {
                 {
                     self.cost_so_far.insert(new_pos, new_cost);
 
-                    let priority =
-                        new_cost + heuristic(end, new_pos, map_width);
+                    // a heuristic that is not consistent can find a cheaper
+                    // way to a node it already expanded, it gets expanded
+                    // again
+                    self.closed.remove(&new_pos);
+
+                    let priority = new_cost + space.heuristic(new_pos, end);
 
                     let new_state = State {
                         cost: priority,
@@ -108,113 +191,93 @@ impl AStar {
 
                     self.frontier.push(new_state);
 
+                    if let Some(replay) = self.replay.as_mut() {
+                        replay.push(new_pos);
+                    }
+
                     self.came_from.insert(new_pos, current.pos);
                 }
             }
         }
 
-        // make_path(&self.came_from, start, end, map_width)
-        make_path(&self.came_from, start, end)
+        path_from(&self.came_from, start, end)
     }
 }
 
-// Manhattan
-fn heuristic(a: MapPos, b: MapPos, width: usize) -> isize {
-    let a_x = (a % width) as isize;
-    let a_y = (a / width) as isize;
+impl AStar {
+    /// what the last run touched, for the renderers
+    pub fn trace(&self) -> SearchTrace {
+        let open = self
+            .frontier
+            .iter()
+            .map(|state| state.pos)
+            .filter(|pos| !self.closed.contains(pos))
+            .collect();
+
+        SearchTrace {
+            closed: self.closed.clone(),
+            open,
+            cost: self.cost_so_far.clone(),
+        }
+    }
+}
 
-    let b_x = (b % width) as isize;
-    let b_y = (b / width) as isize;
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        graph::{Graph, GridSpace},
+        map::{get_map, Grid, Tile},
+    };
+
+    #[test]
+    fn reused_runs_match_fresh_ones() {
+        let map_data = get_map(40, 30, 3);
+        let space = GridSpace::new(&map_data)
+            .with_costs(StepCosts::octile())
+            .with_heuristic(Heuristic::Octile);
+
+        let open: Vec<MapPos> = (0..map_data.len())
+            .filter(|pos| map_data.tile(*pos) != Tile::Wall)
+            .collect();
+        let queries = [
+            (open[0], open[open.len() - 1]),
+            (open[open.len() / 2], open[1]),
+        ];
+
+        let mut reused = AStar::recording();
+
+        for (start, end) in queries {
+            let mut fresh = AStar::recording();
+
+            assert_eq!(
+                reused.run(&space, start, end),
+                fresh.run(&space, start, end)
+            );
+            assert_eq!(reused.closed(), fresh.closed());
+            assert_eq!(reused.cost(end), fresh.cost(end));
+        }
+    }
 
-    (a_x - b_x).abs() + (a_y - b_y).abs()
-}
+    #[test]
+    fn stale_entries_are_skipped() {
+        // b is pushed at 5 then again at 2 through a, the 5 is stale
+        let mut graph = Graph::new();
+        graph.add_edge('s', 'a', 1);
+        graph.add_edge('s', 'b', 5);
+        graph.add_edge('a', 'b', 1);
+        graph.add_edge('b', 'c', 1);
 
-// fn heuristic(a: MapPos, b: MapPos, width: usize) -> isize {
-//     let a_x = (a % width) as isize;
-//     let a_y = (a / width) as isize;
-//
-//     let b_x = (b % width) as isize;
-//     let b_y = (b / width) as isize;
-//
-//     let dx = (a_x - b_x).abs();
-//     let dy = (a_y - b_y).abs();
-//
-//     // 10 * (dx + dy) + 14 * std::cmp::min(dx, dy)
-//     if dx > dy {
-//         14 * dy + 10 * (dx - dy)
-//     } else {
-//         14 * dx + 10 * (dy - dx)
-//     }
-// }
-
-fn offset_position(
-    offset_x: isize,
-    offset_y: isize,
-    current: MapPos,
-    width: usize,
-) -> isize {
-    let c_x = (current % width) as isize;
-    let c_y = (current / width) as isize;
-
-    let n_x = c_x + offset_x;
-    let n_y = c_y + offset_y;
-
-    n_x + (n_y * width as isize)
-}
+        let mut astar = AStar::recording();
 
-// fn smooth_path(
-//     current: (isize, isize),
-//     next: (isize, isize),
-// ) -> (usize, usize) {
-//     let mut ret_x = current.0;
-//     let mut ret_y = current.1;
-//
-//     for (o_x, o_y) in NEIGHBORS {
-//         let cn_x = current.0 + o_x;
-//         let cn_y = current.1 + o_y;
-//
-//         if cn_x == next.0 && cn_y == next.1 {
-//             break;
-//         }
-//     }
-//
-//     (ret_x as usize, ret_y as usize)
-// }
-
-fn make_path(
-    coordinates: &HashMap<MapPos, MapPos>,
-    start: MapPos,
-    end: MapPos,
-    // map_width: usize,
-) -> Vec<MapPos> {
-    let mut path = vec![];
-
-    let mut current = *coordinates.get(&end).unwrap();
-
-    while current != start {
-        path.push(current);
-        current = *coordinates.get(&current).unwrap();
-        // let next = *coordinates.get(&current).unwrap();
-
-        // let c_x = current % map_width;
-        // let c_y = current / map_width;
-
-        // let n_x = next % map_width;
-        // let n_y = next / map_width;
-
-        // let new_current = smooth_path(
-        //     (c_x as isize, c_y as isize),
-        //     (n_x as isize, n_y as isize),
-        // );
-
-        // let new_pos = (new_current.0 + (new_current.1 * map_width)) as usize;
-
-        // path.push(new_pos);
-
-        // current = next;
-    }
+        // nothing reaches z so everything is expanded
+        assert!(astar.run(&graph, 's', 'z').is_empty());
 
-    path.push(start);
+        let replay = astar.replay().unwrap();
+        let expanded: Vec<char> =
+            replay.steps.iter().map(|step| step.expanded).collect();
 
-    path
+        assert_eq!(expanded, ['s', 'a', 'b', 'c']);
+        assert_eq!(astar.cost('b'), Some(2));
+    }
 }
@@ -1,21 +1,14 @@
 // breadth first
 
-use crate::map::{MapData, Tile};
-
-const NEIGHBORS: &[(isize, isize); 8] = &[
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, 1),
-    (1, 0),
-    (1, 1),
-];
+use crate::{
+    coord::{Coord, Direction},
+    map::{Grid, MapData},
+    movement::MovementProfile,
+};
 
 pub struct BreadthFirst {
-    frontier: Vec<(isize, isize)>,
-    reached: Vec<(isize, isize)>,
+    frontier: Vec<Coord>,
+    reached: Vec<Coord>,
 }
 
 impl BreadthFirst {
@@ -26,43 +19,37 @@ impl BreadthFirst {
         }
     }
 
-    pub fn run(&mut self, map: &MapData) -> Vec<(isize, isize)> {
+    pub fn run(&mut self, map: &MapData) -> Vec<Coord> {
+        let profile = MovementProfile::default();
+
         for (i, tile) in map.map.iter().enumerate() {
-            if *tile == Tile::Floor {
-                let x = (i % map.map_width) as isize;
-                let y = (i / map.map_width) as isize;
+            if profile.can_enter(*tile) {
+                let coord = map.coord(i);
 
-                self.frontier.push((x, y));
-                self.reached.push((x, y));
+                self.frontier.push(coord);
+                self.reached.push(coord);
 
                 break;
             }
         }
 
-        let map_size = map.map.len() as isize;
-
-        while !self.frontier.is_empty() {
-            let (c_x, c_y) = self.frontier.pop().unwrap();
+        while let Some(current) = self.frontier.pop() {
+            for dir in Direction::ALL {
+                let next = current.step(dir);
 
-            for (offset_x, offset_y) in NEIGHBORS {
-                let n_x = c_x + offset_x;
-                let n_y = c_y + offset_y;
-
-                let n_index = n_x + (n_y * map.map_width as isize);
-
-                if n_index < 0 || n_index >= map_size {
+                let Some(n_index) = map.index(next) else {
                     continue;
-                }
+                };
 
-                let tile = &map.map[n_index as usize];
+                let tile = &map.map[n_index];
 
-                if *tile == Tile::Wall {
+                if !profile.can_enter(*tile) {
                     continue;
                 }
 
-                if !self.reached.contains(&(n_x, n_y)) {
-                    self.frontier.push((n_x, n_y));
-                    self.reached.push((n_x, n_y));
+                if !self.reached.contains(&next) {
+                    self.frontier.push(next);
+                    self.reached.push(next);
                 }
             }
         }
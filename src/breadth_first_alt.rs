@@ -1,72 +1,152 @@
 // breadth first
 
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet, VecDeque};
 
-use crate::map::{MapData, Tile};
+use crate::{
+    clearance::Clearance,
+    coord::{Coord, Direction},
+    links::AgentState,
+    map::{Grid, MapData},
+    movement::MovementProfile,
+    render::SearchTrace,
+};
 
-const NEIGHBORS: &[(isize, isize); 8] = &[
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, 1),
-    (1, 0),
-    (1, 1),
-];
-
-pub type Coordinates = HashMap<(isize, isize), (isize, isize)>;
+/// where each reached tile was reached from, `None` for the start
+pub type Coordinates = HashMap<Coord, Option<Coord>>;
 
 pub struct BreadthFirst {
-    frontier: Vec<(isize, isize)>,
+    profile: MovementProfile,
+    clearance: Option<(Clearance, usize)>,
+    agent: AgentState,
+    frontier: VecDeque<Coord>,
     came_from: Coordinates,
+    /// steps from the start, tiles are reached in order of it so it is set
+    /// once when a tile is queued
+    depth: HashMap<Coord, isize>,
 }
 
 impl BreadthFirst {
     pub fn new() -> Self {
         Self {
-            frontier: vec![],
+            profile: MovementProfile::default(),
+            clearance: None,
+            agent: AgentState::default(),
+            frontier: VecDeque::new(),
             came_from: HashMap::new(),
+            depth: HashMap::new(),
         }
     }
 
-    pub fn run(
-        &mut self,
-        map: &MapData,
-        start: (isize, isize),
-    ) -> HashMap<(isize, isize), (isize, isize)> {
-        let map_size = map.map.len() as isize;
+    pub fn with_profile(mut self, profile: &MovementProfile) -> Self {
+        self.profile = *profile;
+        self
+    }
+
+    /// only take steps an agent `size` tiles across fits through
+    pub fn with_clearance(mut self, clearance: Clearance, size: usize) -> Self {
+        self.clearance = Some((clearance, size));
+        self
+    }
+
+    /// take the links the agent holds the keys for
+    pub fn with_agent(mut self, agent: AgentState) -> Self {
+        self.agent = agent;
+        self
+    }
 
-        self.frontier.push(start);
+    pub fn run(&mut self, map: &MapData, start: Coord) -> Coordinates {
+        self.frontier.clear();
+        self.came_from.clear();
+        self.depth.clear();
 
-        self.came_from.insert(start, (-1, -1));
+        self.frontier.push_back(start);
 
-        while !self.frontier.is_empty() {
-            let (c_x, c_y) = self.frontier.pop().unwrap();
+        self.came_from.insert(start, None);
+        self.depth.insert(start, 0);
 
-            for (offset_x, offset_y) in NEIGHBORS {
-                let n_x = c_x + offset_x;
-                let n_y = c_y + offset_y;
+        while let Some(current) = self.frontier.pop_front() {
+            let c_index =
+                map.index(current).expect("reached tiles are on the map");
+            let next_depth = self.depth[&current] + 1;
 
-                let n_index = n_x + (n_y * map.map_width as isize);
+            for dir in Direction::ALL {
+                let next = current.step(dir);
 
-                if n_index < 0 || n_index >= map_size {
+                let Some(n_index) = map.index(next) else {
+                    continue;
+                };
+
+                let tile = &map.map[n_index];
+
+                if !self.profile.can_enter(*tile) {
                     continue;
                 }
 
-                let tile = &map.map[n_index as usize];
+                if let Some((clearance, size)) = &self.clearance {
+                    if !clearance.allows(c_index, n_index, *size) {
+                        continue;
+                    }
+                }
 
-                if *tile == Tile::Wall {
+                if !self.came_from.contains_key(&next) {
+                    self.frontier.push_back(next);
+                    self.came_from.insert(next, Some(current));
+                    self.depth.insert(next, next_depth);
+                }
+            }
+
+            for link in map.links.from(c_index) {
+                if !link.allows(&self.agent)
+                    || !self.profile.can_enter(map.map[link.to])
+                {
                     continue;
                 }
 
-                if self.came_from.get(&(n_x, n_y)).is_none() {
-                    self.frontier.push((n_x, n_y));
-                    self.came_from.insert((n_x, n_y), (c_x, c_y));
+                if let Some((clearance, size)) = &self.clearance {
+                    if !clearance.fits(link.to, *size) {
+                        continue;
+                    }
+                }
+
+                let next = map.coord(link.to);
+
+                if !self.came_from.contains_key(&next) {
+                    self.frontier.push_back(next);
+                    self.came_from.insert(next, Some(current));
+                    self.depth.insert(next, next_depth);
                 }
             }
         }
 
         self.came_from.clone()
     }
+
+    /// what the last run touched, for the renderers
+    ///
+    /// breadth first has no costs so the cost is the step count from the
+    /// start
+    pub fn trace(&self, map: &MapData) -> SearchTrace {
+        let to_index = |pos: &Coord| {
+            map.index(*pos).expect("reached tiles are on the map")
+        };
+
+        let open: HashSet<Coord> = self.frontier.iter().copied().collect();
+
+        let cost = self
+            .depth
+            .iter()
+            .map(|(pos, depth)| (to_index(pos), *depth))
+            .collect();
+
+        SearchTrace {
+            closed: self
+                .came_from
+                .keys()
+                .filter(|pos| !open.contains(pos))
+                .map(to_index)
+                .collect(),
+            open: open.iter().map(to_index).collect(),
+            cost,
+        }
+    }
 }
@@ -0,0 +1,159 @@
+// clearance
+//
+// how big an agent can stand on each tile, the side of the largest square
+// with its top left corner on the tile that only covers tiles the agent can
+// enter. an agent n tiles across is anchored by its top left tile and may only
+// stand where the clearance is at least n, so one tile hallways stay closed
+// to anything bigger than a single tile
+
+use crate::{
+    coord::Coord,
+    map::{Grid, MapPos},
+    movement::MovementProfile,
+};
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct Clearance {
+    pub width: usize,
+    pub height: usize,
+    values: Vec<usize>,
+}
+
+impl Clearance {
+    pub fn new<G: Grid + ?Sized>(grid: &G, profile: &MovementProfile) -> Self {
+        let width = grid.width();
+        let height = grid.height();
+        let mut values = vec![0; width * height];
+
+        // each square is one bigger than the smallest of the squares to the
+        // right, below and diagonally below, so fill from the bottom right
+        for y in (0..height).rev() {
+            for x in (0..width).rev() {
+                let pos = x + y * width;
+
+                if !profile.can_enter(grid.tile(pos)) {
+                    continue;
+                }
+
+                let get = |x: usize, y: usize| {
+                    if x < width && y < height {
+                        values[x + y * width]
+                    } else {
+                        0
+                    }
+                };
+
+                values[pos] =
+                    1 + get(x + 1, y).min(get(x, y + 1)).min(get(x + 1, y + 1));
+            }
+        }
+
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+
+    pub fn get(&self, pos: MapPos) -> usize {
+        self.values[pos]
+    }
+
+    /// whether an agent `size` tiles across can stand with its top left
+    /// corner on `pos`
+    pub fn fits(&self, pos: MapPos, size: usize) -> bool {
+        self.values[pos] >= size
+    }
+
+    /// whether an agent `size` tiles across can step from `pos` to the
+    /// neighboring `next`, diagonal steps need room on both sides too
+    pub fn allows(&self, pos: MapPos, next: MapPos, size: usize) -> bool {
+        let here = Coord::from_index(pos, self.width);
+        let there = Coord::from_index(next, self.width);
+
+        let side_fits = |side: Coord| {
+            side.to_index(self.width, self.height)
+                .is_some_and(|side| self.fits(side, size))
+        };
+
+        self.fits(next, size)
+            && (here.x == there.x
+                || here.y == there.y
+                || side_fits(Coord::new(there.x, here.y))
+                    && side_fits(Coord::new(here.x, there.y)))
+    }
+
+    /// the largest agent that fits anywhere on the map
+    pub fn max(&self) -> usize {
+        self.values.iter().copied().max().unwrap_or(0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        astar::AStar,
+        graph::GridSpace,
+        map::{MapData, Tile},
+    };
+
+    const WIDTH: usize = 11;
+
+    // two rooms joined by a hallway one tile wide
+    const NARROW: [&str; 6] = [
+        "###########",
+        "#...###...#",
+        "#.........#",
+        "#...###...#",
+        "#...###...#",
+        "###########",
+    ];
+
+    // the same with a hallway two tiles wide
+    const WIDE: [&str; 6] = [
+        "###########",
+        "#...###...#",
+        "#.........#",
+        "#.........#",
+        "#...###...#",
+        "###########",
+    ];
+
+    fn map(rows: [&str; 6]) -> MapData {
+        let map = rows
+            .concat()
+            .chars()
+            .map(|glyph| Tile::from_glyph(glyph).unwrap())
+            .collect();
+
+        MapData::from_tiles(WIDTH, rows.len(), map)
+    }
+
+    // from the top left of one room to the top left of the other
+    fn path(map_data: &MapData, size: usize) -> Vec<MapPos> {
+        let walker = MovementProfile::walker();
+        let space = GridSpace::new(map_data)
+            .with_profile(&walker)
+            .with_clearance(Clearance::new(map_data, &walker), size);
+
+        AStar::default().run(&space, 1 + WIDTH, 7 + WIDTH)
+    }
+
+    #[test]
+    fn big_agents_do_not_fit_narrow_hallways() {
+        let narrow = map(NARROW);
+        let clearance = Clearance::new(&narrow, &MovementProfile::walker());
+
+        assert_eq!(clearance.get(5 + 2 * WIDTH), 1);
+        assert_eq!(clearance.max(), 3);
+
+        assert!(!path(&narrow, 1).is_empty());
+        assert!(path(&narrow, 2).is_empty());
+
+        let wide = map(WIDE);
+
+        assert!(!path(&wide, 2).is_empty());
+        assert!(path(&wide, 3).is_empty());
+    }
+}
@@ -0,0 +1,1098 @@
+// command line
+//
+// a small hand rolled parser, every flag takes a value except the switches
+// listed in `SWITCHES`
+
+use std::{
+    fs,
+    io::{self, BufWriter, Write},
+    path::{Path, PathBuf},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    astar::AStar,
+    astar::Heuristic,
+    clearance::Clearance,
+    coord::Coord,
+    dijkstra::Dijkstra,
+    explore::Explorer,
+    gen_config::{CorridorShape, GenConfig, RoomShape},
+    generators,
+    graph::{breadth_first, path_from, GridSpace, SearchSpace},
+    heightmap::{
+        self, load_heights, HeightMap, SlopeCosts, SlopeSpace, WeightSpace,
+    },
+    hex::{HexMap, HexSpace},
+    image::{self, map_image},
+    levels::{LayeredMap, LayeredSpace},
+    links::{AgentState, Keys, MAX_KEY},
+    map::{Grid, MapData, MapPos},
+    map_io::{load_map, load_scenarios, save_map_as, MapFormat},
+    movement::{MovementProfile, StepCosts},
+    regions::Regions,
+    render::{render, RenderOptions},
+    room_graph::RoomGraph,
+    sample::{map_distance, Sampler},
+    scenario::{run_scenarios, write_csv, write_table},
+    search::{search_in, search_terrain, Algorithm, SearchOptions},
+};
+
+pub const USAGE: &str = "\
+usage: path_finding <command> [flags]
+
+commands:
+    gen       generate a map and print or save it
+    find      find a path between two points
+    render    find a path and draw what the search explored
+    bench     time the algorithms on random start and end points
+    explore   move around the map and watch the search update live
+    scen      check the searches against a moving ai scenario file
+    height    find a path over a heightmap, corner to corner unless --start
+              and --end are given
+    hex       generate a hex map and find a path on it, points are column
+              and row of the offset layout
+    levels    generate a stack of levels joined by stairs and ladders and
+              find a path from the bottom level to the top one
+
+flags:
+    --width <n>          map width (60)
+    --height <n>         map height (60)
+    --seed <n>           map seed, also picks the random points
+                         (2739832984732098742)
+    --generator <name>   how maps are generated, rooms, bsp, caves,
+                         drunkard, backtracker, prim, kruskal or terrain
+                         (rooms)
+    --rooms <n>          how many rooms rooms tries to place (30)
+    --room-size <n,n>    the smallest and largest rooms (4,8)
+    --loops <n>          hallways on top of the ones joining every room (2)
+    --room-shape <name>  rect, circle or mixed (rect)
+    --corridor <name>    l or straight, how hallways run between rooms (l)
+    --corridor-width <n> how wide hallways are (1)
+    --map <file>         load a map instead of generating one, `.map` files
+                         are read as moving ai maps, `.cmap` as compact maps
+                         and anything else as ascii
+    --start <x,y>        start point, random when missing
+    --end <x,y>          end point, random when missing
+    --min-distance <n>   how far apart random points are at least, in
+                         tiles across plus tiles down (a third of the map's
+                         width and height, closer if nothing is that far)
+    --max-distance <n>   how far apart random points are at most
+    --algorithm <name>   astar, dijkstra, bfs, rooms or all (astar, all for
+                         scen), rooms plans room by room before searching
+                         the tiles, all for scen is only the ones that find
+                         the cheapest path
+    --heuristic <name>   manhattan, chebyshev, octile, euclidean or zero
+                         (manhattan)
+    --octile             walls block, diagonals cost sqrt(2) and can not cut
+                         corners, like the moving ai benchmarks
+    --profile <name>     walker, boat, ghost or tank, how the agent gets
+                         across each terrain
+    --size <n>           agent size in tiles, paths only go where an n by n
+                         agent fits (1)
+    --keys <n,n..>       the keys the agent holds, locked links in the map
+                         need one
+    --format <name>      ascii, map, cmap, ppm, png, gif or csv (ascii)
+    --out <file>         where to write images, the format is taken from the
+                         extension when --format is missing
+    --scale <n>          pixels per tile for images (4)
+    --stride <n>         expansions per gif frame (10)
+    --runs <n>           runs per algorithm for bench (20)
+    --scen <file>        the scenario file for scen, the map listed in it is
+                         loaded from next to it unless --map is given
+    --heights <file>     load a heightmap for height instead of generating
+                         one, rows of numbers separated by whitespace
+    --climb <n>          extra cost per unit of height climbed (1)
+    --max-climb <n>      the steepest slope a step can climb
+    --weights            read heightmap values as cell costs, not heights
+    --levels <n>         how many levels to stack for levels (3)
+    --heat               color tiles by search cost
+    --no-color           plain ascii output
+    --help               show this message
+";
+
+const SWITCHES: &[&str] = &["heat", "no-color", "octile", "weights", "help"];
+
+const DEFAULT_SEED: u64 = 2739832984732098742;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Command {
+    Gen,
+    Find,
+    Render,
+    Bench,
+    Explore,
+    Scen,
+    Height,
+    Hex,
+    Levels,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Format {
+    Ascii,
+    MovingAi,
+    Compact,
+    Ppm,
+    Png,
+    Gif,
+    Csv,
+}
+
+impl Format {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "ascii" | "txt" => Some(Format::Ascii),
+            "map" => Some(Format::MovingAi),
+            "cmap" => Some(Format::Compact),
+            "ppm" => Some(Format::Ppm),
+            "png" => Some(Format::Png),
+            "gif" => Some(Format::Gif),
+            "csv" => Some(Format::Csv),
+            _ => None,
+        }
+    }
+
+    pub fn ext(self) -> &'static str {
+        match self {
+            Format::Ascii => "txt",
+            Format::MovingAi => "map",
+            Format::Compact => "cmap",
+            Format::Ppm => "ppm",
+            Format::Png => "png",
+            Format::Gif => "gif",
+            Format::Csv => "csv",
+        }
+    }
+
+    /// the map file format this is, `None` for images and tables
+    pub fn map_format(self) -> Option<MapFormat> {
+        match self {
+            Format::Ascii => Some(MapFormat::Ascii),
+            Format::MovingAi => Some(MapFormat::MovingAi),
+            Format::Compact => Some(MapFormat::Compact),
+            Format::Ppm | Format::Png | Format::Gif | Format::Csv => None,
+        }
+    }
+}
+
+#[derive(Debug)]
+pub struct Args {
+    pub command: Command,
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    pub generator: String,
+    pub gen_config: GenConfig,
+    pub map: Option<String>,
+    pub start: Option<Coord>,
+    pub end: Option<Coord>,
+    pub algorithm: Algorithm,
+    pub all_algorithms: bool,
+    pub heuristic: Heuristic,
+    pub costs: Option<StepCosts>,
+    pub profile: Option<MovementProfile>,
+    pub agent_size: usize,
+    pub keys: Keys,
+    pub min_distance: Option<isize>,
+    pub max_distance: Option<isize>,
+    pub format: Format,
+    pub out: Option<String>,
+    pub scale: usize,
+    pub stride: usize,
+    pub runs: usize,
+    pub scen: Option<String>,
+    pub heights: Option<String>,
+    pub slope: SlopeCosts,
+    pub weights: bool,
+    pub levels: usize,
+    pub heat: bool,
+    pub color: bool,
+}
+
+impl Default for Args {
+    fn default() -> Self {
+        Self {
+            command: Command::Find,
+            width: 60,
+            height: 60,
+            seed: DEFAULT_SEED,
+            generator: "rooms".to_string(),
+            gen_config: GenConfig::default(),
+            map: None,
+            start: None,
+            end: None,
+            algorithm: Algorithm::AStar,
+            all_algorithms: false,
+            heuristic: Heuristic::Manhattan,
+            costs: None,
+            profile: None,
+            agent_size: 1,
+            keys: Keys::default(),
+            min_distance: None,
+            max_distance: None,
+            format: Format::Ascii,
+            out: None,
+            scale: 4,
+            stride: 10,
+            runs: 20,
+            scen: None,
+            heights: None,
+            slope: SlopeCosts::default(),
+            weights: false,
+            levels: 3,
+            heat: false,
+            color: true,
+        }
+    }
+}
+
+impl Args {
+    pub fn search_options(&self) -> SearchOptions {
+        SearchOptions {
+            heuristic: self.heuristic,
+            costs: self.costs,
+            profile: self.profile,
+            agent_size: self.agent_size,
+            agent: AgentState { keys: self.keys },
+            record: false,
+        }
+    }
+}
+
+/// parse the arguments, without the program name, `Ok(None)` means help
+/// was asked for
+pub fn parse<I: IntoIterator<Item = String>>(
+    args: I,
+) -> Result<Option<Args>, String> {
+    let mut parsed = Args::default();
+    // kept in the order they were given so the first bad flag is the one
+    // reported
+    let mut flags: Vec<(String, String)> = vec![];
+
+    let mut args = args.into_iter().peekable();
+
+    if let Some(first) = args.peek() {
+        if !first.starts_with("--") {
+            parsed.command = match first.as_str() {
+                "gen" => Command::Gen,
+                "find" => Command::Find,
+                "render" => Command::Render,
+                "bench" => Command::Bench,
+                "explore" => Command::Explore,
+                "scen" => Command::Scen,
+                "height" => Command::Height,
+                "hex" => Command::Hex,
+                "levels" => Command::Levels,
+                other => return Err(format!("unknown command: {}", other)),
+            };
+
+            args.next();
+        }
+    }
+
+    while let Some(arg) = args.next() {
+        let flag = match arg.strip_prefix("--") {
+            Some(flag) => flag,
+            None => return Err(format!("unexpected argument: {}", arg)),
+        };
+
+        let (name, value) = match flag.split_once('=') {
+            Some((name, value)) => (name.to_string(), value.to_string()),
+            None if SWITCHES.contains(&flag) => {
+                (flag.to_string(), String::new())
+            }
+            None => match args.next() {
+                Some(value) => (flag.to_string(), value),
+                None => return Err(format!("--{} needs a value", flag)),
+            },
+        };
+
+        flags.push((name, value));
+    }
+
+    for (name, value) in &flags {
+        match name.as_str() {
+            "help" => return Ok(None),
+            "width" => parsed.width = parse_num(name, value)?,
+            "height" => parsed.height = parse_num(name, value)?,
+            "seed" => parsed.seed = parse_num(name, value)?,
+            "generator" if generators::NAMES.contains(&value.as_str()) => {
+                parsed.generator = value.clone()
+            }
+            "generator" => return Err(format!("unknown generator: {}", value)),
+            "rooms" => {
+                parsed.gen_config =
+                    parsed.gen_config.with_max_rooms(parse_num(name, value)?)
+            }
+            "room-size" => {
+                let (min, max) = value.split_once(',').ok_or(format!(
+                    "--{} expects min,max, got {}",
+                    name, value
+                ))?;
+
+                parsed.gen_config = parsed.gen_config.with_room_size(
+                    parse_num(name, min.trim())?,
+                    parse_num(name, max.trim())?,
+                )
+            }
+            "loops" => {
+                parsed.gen_config =
+                    parsed.gen_config.with_loops(parse_num(name, value)?)
+            }
+            "room-shape" => {
+                parsed.gen_config = parsed.gen_config.with_room_shape(
+                    RoomShape::from_name(value)
+                        .ok_or(format!("unknown room shape: {}", value))?,
+                )
+            }
+            "corridor" => {
+                parsed.gen_config = parsed.gen_config.with_corridor_shape(
+                    CorridorShape::from_name(value)
+                        .ok_or(format!("unknown corridor: {}", value))?,
+                )
+            }
+            "corridor-width" => {
+                parsed.gen_config = parsed
+                    .gen_config
+                    .with_corridor_width(parse_num(name, value)?)
+            }
+            "map" => parsed.map = Some(value.clone()),
+            "start" => parsed.start = Some(parse_point(name, value)?),
+            "min-distance" => {
+                parsed.min_distance = Some(parse_num(name, value)?)
+            }
+            "max-distance" => {
+                parsed.max_distance = Some(parse_num(name, value)?)
+            }
+            "end" => parsed.end = Some(parse_point(name, value)?),
+            "algorithm" if value == "all" => parsed.all_algorithms = true,
+            "algorithm" => {
+                parsed.algorithm = Algorithm::from_name(value)
+                    .ok_or(format!("unknown algorithm: {}", value))?
+            }
+            "heuristic" => {
+                parsed.heuristic = Heuristic::from_name(value)
+                    .ok_or(format!("unknown heuristic: {}", value))?
+            }
+            "format" => {
+                parsed.format = Format::from_name(value)
+                    .ok_or(format!("unknown format: {}", value))?
+            }
+            "out" => parsed.out = Some(value.clone()),
+            "scale" => parsed.scale = parse_num(name, value)?,
+            "stride" => parsed.stride = parse_num(name, value)?,
+            "runs" => parsed.runs = parse_num(name, value)?,
+            "scen" => parsed.scen = Some(value.clone()),
+            "octile" => parsed.costs = Some(StepCosts::octile()),
+            "profile" => {
+                parsed.profile = Some(
+                    MovementProfile::from_name(value)
+                        .ok_or(format!("unknown profile: {}", value))?,
+                )
+            }
+            "size" => parsed.agent_size = parse_num(name, value)?,
+            "keys" => {
+                for key in value.split(',') {
+                    let key: u8 = parse_num(name, key.trim())?;
+
+                    if key > MAX_KEY {
+                        return Err(format!("keys go up to {}", MAX_KEY));
+                    }
+
+                    parsed.keys = parsed.keys.with(key);
+                }
+            }
+            "heights" => parsed.heights = Some(value.clone()),
+            "climb" => parsed.slope.climb = parse_num(name, value)?,
+            "max-climb" => {
+                parsed.slope.max_climb = Some(parse_num(name, value)?)
+            }
+            "weights" => parsed.weights = true,
+            "levels" => parsed.levels = parse_num(name, value)?,
+            "heat" => parsed.heat = true,
+            "no-color" => parsed.color = false,
+            other => return Err(format!("unknown flag: --{}", other)),
+        }
+    }
+
+    parsed
+        .slope
+        .validate()
+        .map_err(|err| format!("--{}", err))?;
+
+    // scen checks everything unless told otherwise
+    if parsed.command == Command::Scen
+        && !flags.iter().any(|(name, _)| name == "algorithm")
+    {
+        parsed.all_algorithms = true;
+    }
+
+    // let the file name pick the format unless it was given
+    if !flags.iter().any(|(name, _)| name == "format") {
+        if let Some(ext) = parsed
+            .out
+            .as_deref()
+            .and_then(|out| Path::new(out).extension())
+            .and_then(|ext| ext.to_str())
+        {
+            parsed.format = Format::from_name(ext).unwrap_or(Format::Ascii);
+        }
+    }
+
+    if parsed.format != Format::Ascii && parsed.out.is_none() {
+        return Err(format!(
+            "--format {} needs --out <file>",
+            parsed.format.ext()
+        ));
+    }
+
+    Ok(Some(parsed))
+}
+
+fn parse_num<T: std::str::FromStr>(
+    name: &str,
+    value: &str,
+) -> Result<T, String> {
+    value
+        .parse()
+        .map_err(|_| format!("--{} expects a number, got {}", name, value))
+}
+
+fn parse_point(name: &str, value: &str) -> Result<Coord, String> {
+    let (x, y) = value
+        .split_once(',')
+        .ok_or(format!("--{} expects x,y, got {}", name, value))?;
+
+    Ok(Coord::new(
+        parse_num(name, x.trim())?,
+        parse_num(name, y.trim())?,
+    ))
+}
+
+// the index of a point given on the command line
+fn checked_point(
+    point: Coord,
+    width: usize,
+    height: usize,
+    name: &str,
+) -> Result<MapPos, String> {
+    point
+        .to_index(width, height)
+        .ok_or(format!("{} {} is outside the map", name, point))
+}
+
+pub fn run(args: &Args) -> Result<(), String> {
+    match args.command {
+        Command::Gen => gen(args, &map_data(args)?),
+        Command::Find => find(args, &map_data(args)?, false),
+        Command::Render => find(args, &map_data(args)?, true),
+        Command::Bench => bench(args, &map_data(args)?),
+        Command::Explore => Explorer::new(
+            map_data(args)?,
+            args.algorithm,
+            args.search_options(),
+        )
+        .run()
+        .map_err(|err| err.to_string()),
+        Command::Scen => scen(args),
+        Command::Height => height(args),
+        Command::Hex => hex(args),
+        Command::Levels => levels(args),
+    }
+}
+
+// the map `--map` names or a freshly generated one
+fn map_data(args: &Args) -> Result<MapData, String> {
+    match &args.map {
+        Some(file) => load_map(file)
+            .map_err(|err| format!("could not load {}: {}", file, err)),
+        None => generators::from_name(&args.generator, args.gen_config)
+            .expect("generators are checked when parsing")
+            .generate(args.width, args.height, args.seed),
+    }
+}
+
+fn levels(args: &Args) -> Result<(), String> {
+    if args.levels == 0 {
+        return Err("--levels needs at least one level".to_string());
+    }
+
+    let map =
+        LayeredMap::generate(args.width, args.height, args.levels, args.seed)?;
+    let profile = args.profile.unwrap_or_default();
+    let top = map.levels.len() - 1;
+
+    let point = |point: Option<Coord>, level: usize, name| {
+        point
+            .map(|point| {
+                let pos = checked_point(point, map.width, map.height, name)?;
+
+                Ok::<_, String>(map.node(pos, level))
+            })
+            .transpose()
+    };
+
+    let open = |level: usize| -> Vec<usize> {
+        (0..map.width * map.height)
+            .filter(|pos| profile.can_enter(map.levels[level].map[*pos]))
+            .map(|pos| map.node(pos, level))
+            .collect()
+    };
+
+    let space = LayeredSpace::new(&map)
+        .with_heuristic(args.heuristic)
+        .with_costs(args.costs.unwrap_or_default())
+        .with_profile(&profile);
+
+    let (start, end) = sample_points(
+        args,
+        &space,
+        (point(args.start, 0, "start")?, point(args.end, top, "end")?),
+        (&open(0), &open(top)),
+        |a, b| {
+            let (a, b) = (map.split(a).0, map.split(b).0);
+
+            map.levels[0].coord(a).manhattan(map.levels[0].coord(b))
+        },
+        0,
+        args.seed,
+    )?;
+
+    let now = Instant::now();
+
+    let path = match args.algorithm {
+        Algorithm::AStar => AStar::default().run(&space, start, end),
+        Algorithm::Dijkstra => {
+            path_from(&Dijkstra::new().run(&space, start, end), start, end)
+        }
+        Algorithm::BreadthFirst => {
+            path_from(&breadth_first(&space, start, end), start, end)
+        }
+        Algorithm::Rooms => {
+            return Err("rooms only plans on single level maps".to_string())
+        }
+    };
+
+    println!(
+        "{} from {} on level 0 to {} on level {}",
+        args.algorithm.name(),
+        map.levels[0].coord(start),
+        map.levels[top].coord(map.split(end).0),
+        top
+    );
+
+    if path.is_empty() {
+        println!("no path found");
+    } else {
+        println!("steps {} in {:?}", path.len() - 1, now.elapsed());
+    }
+
+    let options = RenderOptions {
+        color: args.color,
+        ..RenderOptions::default()
+    };
+
+    match args.format {
+        Format::Ascii => output_text(args, &map.render(&path, options)),
+        _ => Err(format!("levels can not write {}", args.format.ext())),
+    }
+}
+
+fn hex(args: &Args) -> Result<(), String> {
+    if args.width < 8 || args.height < 8 {
+        return Err("hex maps need to be at least 8 by 8".to_string());
+    }
+
+    let mut map = HexMap::new(args.width, args.height, args.seed);
+    map.gen();
+
+    let profile = args.profile.unwrap_or_default();
+    let open: Vec<MapPos> = (0..map.tiles.len())
+        .filter(|pos| profile.can_enter(map.tiles[*pos]))
+        .collect();
+
+    let point = |point: Option<Coord>, name| {
+        point
+            .map(|point| checked_point(point, map.width, map.height, name))
+            .transpose()
+    };
+
+    let space = HexSpace::new(&map).with_profile(&profile);
+
+    let (start, end) = sample_points(
+        args,
+        &space,
+        (point(args.start, "start")?, point(args.end, "end")?),
+        (&open, &open),
+        |a, b| map.hex(a).distance(map.hex(b)),
+        0,
+        args.seed,
+    )?;
+
+    let now = Instant::now();
+
+    let path = match args.algorithm {
+        Algorithm::AStar => AStar::default().run(&space, start, end),
+        Algorithm::Dijkstra => {
+            path_from(&Dijkstra::new().run(&space, start, end), start, end)
+        }
+        Algorithm::BreadthFirst => {
+            path_from(&breadth_first(&space, start, end), start, end)
+        }
+        Algorithm::Rooms => {
+            return Err("rooms only plans on square grids".to_string())
+        }
+    };
+
+    println!(
+        "{} from {} to {}",
+        args.algorithm.name(),
+        Coord::from_index(start, map.width),
+        Coord::from_index(end, map.width)
+    );
+
+    if path.is_empty() {
+        println!("no path found");
+    } else {
+        println!("steps {} in {:?}", path.len() - 1, now.elapsed());
+    }
+
+    match args.format {
+        Format::Ascii => output_text(args, &map.render(&path, args.color)),
+        Format::Ppm | Format::Png => {
+            save_image(args, &map.image(&path).scaled(args.scale))
+        }
+        _ => Err(format!("hex can not write {}", args.format.ext())),
+    }
+}
+
+fn height(args: &Args) -> Result<(), String> {
+    let heights = match &args.heights {
+        Some(file) => load_heights(file)
+            .map_err(|err| format!("could not load {}: {}", file, err))?,
+        None => HeightMap::generate(args.width, args.height, args.seed),
+    };
+
+    let checked = |point: Option<Coord>, default, name| {
+        let point = point.unwrap_or(default);
+
+        checked_point(point, heights.width, heights.height, name)
+    };
+
+    let far_corner =
+        Coord::new(heights.width as isize - 1, heights.height as isize - 1);
+
+    let start = checked(args.start, Coord::new(0, 0), "start")?;
+    let end = checked(args.end, far_corner, "end")?;
+
+    let now = Instant::now();
+
+    let (path, cost, expanded) = match args.algorithm {
+        Algorithm::AStar => {
+            let mut astar = AStar::default();
+            let path = if args.weights {
+                astar.run(&WeightSpace::new(&heights), start, end)
+            } else {
+                astar.run(&SlopeSpace::new(&heights, args.slope)?, start, end)
+            };
+
+            (path, astar.cost(end).unwrap_or(0), astar.closed().len())
+        }
+        Algorithm::Dijkstra => {
+            let mut dijkstra = Dijkstra::new();
+            let came_from = if args.weights {
+                dijkstra.run(&WeightSpace::new(&heights), start, end)
+            } else {
+                dijkstra.run(
+                    &SlopeSpace::new(&heights, args.slope)?,
+                    start,
+                    end,
+                )
+            };
+
+            (
+                path_from(&came_from, start, end),
+                dijkstra.cost(end).unwrap_or(0) as isize,
+                dijkstra.closed().len(),
+            )
+        }
+        Algorithm::BreadthFirst | Algorithm::Rooms => {
+            return Err(format!(
+                "{} can not search heightmaps",
+                args.algorithm.name()
+            ))
+        }
+    };
+
+    let elapsed = now.elapsed();
+
+    if path.is_empty() {
+        println!("no path found");
+    } else {
+        println!(
+            "steps {} cost {:.2} expanded {} in {:?}",
+            path.len() - 1,
+            cost as f64 / heightmap::STEP as f64,
+            expanded,
+            elapsed
+        );
+    }
+
+    match args.format {
+        Format::Ascii => output_text(args, &heights.render(&path, args.color)),
+        Format::Ppm | Format::Png => {
+            let mut image = heights.image();
+            image::draw_path(&mut image, &path, image::PATH_COLORS[0]);
+
+            save_image(args, &image.scaled(args.scale))
+        }
+        _ => Err(format!("height can not write {}", args.format.ext())),
+    }
+}
+
+fn scen(args: &Args) -> Result<(), String> {
+    let file = args.scen.as_deref().ok_or("scen needs --scen <file>")?;
+
+    let scenarios = load_scenarios(file)
+        .map_err(|err| format!("could not load {}: {}", file, err))?;
+
+    let map_file = match (&args.map, scenarios.first()) {
+        (Some(map), _) => PathBuf::from(map),
+        (None, Some(first)) => Path::new(file)
+            .parent()
+            .unwrap_or_else(|| Path::new(""))
+            .join(&first.map),
+        (None, None) => return Err(format!("{} has no scenarios", file)),
+    };
+
+    let map_data = load_map(&map_file).map_err(|err| {
+        format!("could not load {}: {}", map_file.display(), err)
+    })?;
+
+    // the others find paths that are longer than the optimal length on
+    // purpose, they can still be asked for one at a time
+    let algorithms = if args.all_algorithms {
+        Algorithm::CHEAPEST
+    } else {
+        std::slice::from_ref(&args.algorithm)
+    };
+
+    let results = run_scenarios(&map_data, &scenarios, algorithms);
+
+    let mut table = vec![];
+    write_table(&mut table, &results, algorithms)
+        .map_err(|err| err.to_string())?;
+    print!("{}", String::from_utf8_lossy(&table));
+
+    if let Some(out) = &args.out {
+        let mut csv = vec![];
+        write_csv(&mut csv, &results).map_err(|err| err.to_string())?;
+
+        fs::write(out, csv)
+            .map_err(|err| format!("could not write {}: {}", out, err))?;
+    }
+
+    Ok(())
+}
+
+fn gen(args: &Args, map_data: &MapData) -> Result<(), String> {
+    let options = RenderOptions {
+        color: args.color,
+        ..RenderOptions::default()
+    };
+
+    match args.format {
+        Format::Ascii | Format::MovingAi | Format::Compact
+            if args.out.is_some() =>
+        {
+            let out = args.out.as_deref().unwrap_or_default();
+            let format = args.format.map_format().expect("a map format");
+
+            save_map_as(out, map_data, format)
+                .map_err(|err| format!("could not write {}: {}", out, err))
+        }
+        Format::Ascii | Format::MovingAi | Format::Compact => {
+            output_text(args, &render(map_data, &[], None, options))
+        }
+        Format::Ppm | Format::Png => {
+            save_image(args, &map_image(map_data).scaled(args.scale))
+        }
+        Format::Gif | Format::Csv => {
+            Err(format!("gen can not write {}", args.format.ext()))
+        }
+    }
+}
+
+fn find(args: &Args, map_data: &MapData, explored: bool) -> Result<(), String> {
+    let (start, end) = endpoints(args, map_data, args.seed)?;
+
+    println!(
+        "{} from {} to {}",
+        args.algorithm.name(),
+        map_data.coord(start),
+        map_data.coord(end)
+    );
+
+    let options = SearchOptions {
+        record: args.format == Format::Gif,
+        ..args.search_options()
+    };
+    let regions =
+        Regions::new(map_data, search_terrain(args.algorithm, &options));
+    let found = search_in(
+        args.algorithm,
+        map_data,
+        &regions,
+        None,
+        start,
+        end,
+        &options,
+    );
+
+    if found.path.is_empty() {
+        println!("no path found");
+    } else {
+        println!(
+            "steps {} expanded {} in {:?}",
+            found.path.len() - 1,
+            found.trace.closed.len(),
+            found.elapsed
+        );
+    }
+
+    let trace = if explored { Some(&found.trace) } else { None };
+
+    match args.format {
+        Format::Ascii => {
+            let options = RenderOptions {
+                color: args.color,
+                heat: args.heat,
+                ..RenderOptions::default()
+            };
+
+            output_text(args, &render(map_data, &found.path, trace, options))
+        }
+        Format::Ppm | Format::Png => {
+            let mut image = map_image(map_data);
+
+            if let Some(trace) = trace {
+                if args.heat {
+                    image::draw_heat(&mut image, map_data, trace);
+                } else {
+                    image::draw_trace(&mut image, trace);
+                }
+            }
+
+            image::draw_path(&mut image, &found.path, image::PATH_COLORS[0]);
+
+            save_image(args, &image.scaled(args.scale))
+        }
+        Format::Gif => {
+            let replay = found
+                .replay
+                .ok_or(format!("{} can not record", args.algorithm.name()))?;
+
+            let out = args.out.as_deref().unwrap_or_default();
+
+            replay
+                .save_gif(
+                    out,
+                    map_data,
+                    &found.path,
+                    args.stride,
+                    args.scale,
+                    4,
+                )
+                .map_err(|err| format!("could not write {}: {}", out, err))
+        }
+        Format::MovingAi | Format::Compact => Err(format!(
+            "{} can not write {}, gen saves maps",
+            if explored { "render" } else { "find" },
+            args.format.ext()
+        )),
+        Format::Csv => Err("only scen writes csv".to_string()),
+    }
+}
+
+fn bench(args: &Args, map_data: &MapData) -> Result<(), String> {
+    // a seed per run so every run gets its own pair
+    let pairs: Vec<(MapPos, MapPos)> = (0..args.runs as u64)
+        .map(|run| endpoints(args, map_data, args.seed.wrapping_add(run)))
+        .collect::<Result<_, _>>()?;
+
+    println!(
+        "{:<10} {:>6} {:>12} {:>12} {:>10}",
+        "algorithm", "runs", "avg time", "avg expanded", "avg steps"
+    );
+
+    let options = args.search_options();
+
+    for algorithm in Algorithm::ALL {
+        let mut elapsed = Duration::ZERO;
+        let mut expanded = 0;
+        let mut steps = 0;
+
+        // labelled once, every run after that asks them first
+        let regions =
+            Regions::new(map_data, search_terrain(*algorithm, &options));
+        let rooms =
+            (*algorithm == Algorithm::Rooms).then(|| RoomGraph::new(map_data));
+
+        for (start, end) in &pairs {
+            let found = search_in(
+                *algorithm,
+                map_data,
+                &regions,
+                rooms.as_ref(),
+                *start,
+                *end,
+                &options,
+            );
+
+            elapsed += found.elapsed;
+            expanded += found.trace.closed.len();
+            steps += found.path.len().saturating_sub(1);
+        }
+
+        let runs = pairs.len().max(1);
+
+        println!(
+            "{:<10} {:>6} {:>12?} {:>12} {:>10}",
+            algorithm.name(),
+            pairs.len(),
+            elapsed / runs as u32,
+            expanded / runs,
+            steps / runs
+        );
+    }
+
+    Ok(())
+}
+
+fn endpoints(
+    args: &Args,
+    map_data: &MapData,
+    seed: u64,
+) -> Result<(MapPos, MapPos), String> {
+    let profile = args.profile.unwrap_or_default();
+    let size = args.agent_size.max(1);
+    let clearance = Clearance::new(map_data, &profile);
+
+    let candidates: Vec<MapPos> = (0..map_data.len())
+        .filter(|pos| clearance.fits(*pos, size))
+        .collect();
+
+    let start = args
+        .start
+        .map(|point| checked_index(map_data, point, "start"))
+        .transpose()?;
+    let end = args
+        .end
+        .map(|point| checked_index(map_data, point, "end"))
+        .transpose()?;
+
+    let space = GridSpace::new(map_data)
+        .with_profile(&profile)
+        .with_clearance(clearance, size)
+        .with_agent(AgentState { keys: args.keys });
+
+    sample_points(
+        args,
+        &space,
+        (start, end),
+        (&candidates, &candidates),
+        map_distance(map_data),
+        // far apart when the map is big enough for it
+        (map_data.map_width + map_data.map_height) as isize / 3,
+        seed,
+    )
+}
+
+// the start and end given on the command line, whichever is missing is
+// sampled from `starts` or `ends` so there is a path between them
+//
+// without --min-distance pairs at least `far` apart are tried first
+fn sample_points<S, D>(
+    args: &Args,
+    space: &S,
+    (start, end): (Option<S::Node>, Option<S::Node>),
+    (starts, ends): (&[S::Node], &[S::Node]),
+    distance: D,
+    far: isize,
+    seed: u64,
+) -> Result<(S::Node, S::Node), String>
+where
+    S: SearchSpace,
+    D: Fn(S::Node, S::Node) -> isize,
+{
+    let sample = |min_distance: isize| {
+        let mut sampler = Sampler::new(seed)
+            .with_min_distance(min_distance)
+            .with_max_distance(args.max_distance.unwrap_or(isize::MAX));
+
+        match (start, end) {
+            (Some(start), Some(end)) => Some((start, end)),
+            (Some(start), None) => sampler
+                .goal(space, start, ends, &distance)
+                .map(|end| (start, end)),
+            (None, Some(end)) => sampler
+                .start(space, end, starts, &distance)
+                .map(|start| (start, end)),
+            (None, None) => {
+                sampler.pair_between(space, starts, ends, &distance)
+            }
+        }
+    };
+
+    let found = match args.min_distance {
+        Some(min_distance) => sample(min_distance),
+        None => sample(far).or_else(|| sample(0)),
+    };
+
+    found.ok_or_else(|| {
+        if starts.is_empty() || ends.is_empty() {
+            "the map has nowhere the agent can stand".to_string()
+        } else {
+            "found no start and end in range with a path between them"
+                .to_string()
+        }
+    })
+}
+
+fn checked_index(
+    map_data: &MapData,
+    point: Coord,
+    name: &str,
+) -> Result<MapPos, String> {
+    checked_point(point, map_data.map_width, map_data.map_height, name)
+}
+
+fn output_text(args: &Args, text: &str) -> Result<(), String> {
+    match &args.out {
+        Some(out) => fs::write(out, text)
+            .map_err(|err| format!("could not write {}: {}", out, err)),
+        None => {
+            print!("{}", text);
+            Ok(())
+        }
+    }
+}
+
+// the format was already taken from the extension when --format was missing
+fn save_image(args: &Args, image: &image::Image) -> Result<(), String> {
+    let out = args.out.as_deref().unwrap_or_default();
+
+    let write = || -> io::Result<()> {
+        let mut writer = BufWriter::new(fs::File::create(out)?);
+
+        match args.format {
+            Format::Png => image.write_png(&mut writer)?,
+            _ => image.write_ppm(&mut writer)?,
+        }
+
+        writer.flush()
+    };
+
+    write().map_err(|err| format!("could not write {}: {}", out, err))
+}
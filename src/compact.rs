@@ -0,0 +1,441 @@
+// compact maps
+//
+// a bit packed passability grid the searches can walk directly, and an on
+// disk format for it
+//
+// the file is a header followed by the tiles:
+//
+//     magic     b"PFMP"
+//     version   u8
+//     encoding  u8, 0 for packed bits and 1 for run lengths
+//     width     u32 little endian
+//     height    u32 little endian
+//     seed      u64 little endian
+//
+// packed bits are one bit per tile, row major, a set bit is a wall. run
+// lengths alternate between walls and floor starting with walls, each run is
+// a LEB128 varint so the first run can be zero
+
+use std::{
+    fs::File,
+    io::{self, BufReader, BufWriter, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    map::{Grid, MapData, MapPos, Tile},
+    movement::MovementProfile,
+};
+
+pub const MAGIC: &[u8; 4] = b"PFMP";
+pub const VERSION: u8 = 1;
+
+/// the most tiles a file is trusted with, far more than the biggest
+/// benchmark maps but small enough that a bad header can not take all the
+/// memory
+pub const MAX_TILES: usize = 1 << 30;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Encoding {
+    Bits = 0,
+    RunLength = 1,
+}
+
+/// one bit per tile, set for walls
+#[derive(Clone, PartialEq, Eq, Debug)]
+pub struct BitGrid {
+    pub width: usize,
+    pub height: usize,
+    pub seed: u64,
+    words: Vec<u64>,
+}
+
+impl BitGrid {
+    /// an all wall grid
+    pub fn new(width: usize, height: usize) -> Self {
+        Self {
+            width,
+            height,
+            seed: 0,
+            words: vec![u64::MAX; (width * height).div_ceil(64)],
+        }
+    }
+
+    /// only passability survives, terrain a walker can not enter becomes wall
+    /// and everything else floor
+    pub fn from_map(map_data: &MapData) -> Self {
+        let profile = MovementProfile::walker();
+
+        let mut grid = Self::new(map_data.map_width, map_data.map_height);
+        grid.seed = map_data.seed;
+
+        for (pos, tile) in map_data.map.iter().enumerate() {
+            grid.set_wall(pos, !profile.can_enter(*tile));
+        }
+
+        grid
+    }
+
+    pub fn to_map(&self) -> MapData {
+        let map = (0..self.len()).map(|pos| self.tile(pos)).collect();
+
+        MapData {
+            seed: self.seed,
+            ..MapData::from_tiles(self.width, self.height, map)
+        }
+    }
+
+    pub fn is_wall(&self, pos: MapPos) -> bool {
+        self.words[pos / 64] & (1 << (pos % 64)) != 0
+    }
+
+    pub fn set_wall(&mut self, pos: MapPos, wall: bool) {
+        if wall {
+            self.words[pos / 64] |= 1 << (pos % 64);
+        } else {
+            self.words[pos / 64] &= !(1 << (pos % 64));
+        }
+    }
+
+    /// how many bytes the tiles take in memory
+    pub fn byte_len(&self) -> usize {
+        self.words.len() * 8
+    }
+
+    // lengths of the alternating wall and floor runs, walls first, each one
+    // handed out as it ends
+    fn runs(&self) -> impl Iterator<Item = u64> + '_ {
+        let mut pos = 0;
+        let mut wall = true;
+        let mut done = false;
+
+        std::iter::from_fn(move || {
+            if done {
+                return None;
+            }
+
+            let start = pos;
+
+            while pos < self.len() && self.is_wall(pos) == wall {
+                pos += 1;
+            }
+
+            wall = !wall;
+            done = pos == self.len();
+
+            Some((pos - start) as u64)
+        })
+    }
+}
+
+impl Grid for BitGrid {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn tile(&self, pos: MapPos) -> Tile {
+        if self.is_wall(pos) {
+            Tile::Wall
+        } else {
+            Tile::Floor
+        }
+    }
+}
+
+fn invalid(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
+
+fn write_varint<W: Write>(writer: &mut W, mut value: u64) -> io::Result<()> {
+    loop {
+        let byte = (value & 0x7f) as u8;
+        value >>= 7;
+
+        if value == 0 {
+            return writer.write_all(&[byte]);
+        }
+
+        writer.write_all(&[byte | 0x80])?;
+    }
+}
+
+fn read_varint<R: Read>(reader: &mut R) -> io::Result<u64> {
+    let mut value = 0;
+
+    for shift in (0..64).step_by(7) {
+        let mut byte = [0; 1];
+        reader.read_exact(&mut byte)?;
+
+        value |= ((byte[0] & 0x7f) as u64) << shift;
+
+        if byte[0] & 0x80 == 0 {
+            return Ok(value);
+        }
+    }
+
+    Err(invalid("varint is too long"))
+}
+
+/// write the grid, `None` picks whichever encoding comes out smaller
+pub fn write_compact<W: Write>(
+    writer: &mut W,
+    grid: &BitGrid,
+    encoding: Option<Encoding>,
+) -> io::Result<()> {
+    // the header has 32 bits for each side
+    let (Ok(width), Ok(height)) =
+        (u32::try_from(grid.width), u32::try_from(grid.height))
+    else {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidInput,
+            format!(
+                "a {} by {} map does not fit a compact map",
+                grid.width, grid.height
+            ),
+        ));
+    };
+
+    let encoding = encoding.unwrap_or_else(|| {
+        // a varint is at least one byte so this is a good enough guess
+        if grid.runs().count() < grid.len().div_ceil(8) {
+            Encoding::RunLength
+        } else {
+            Encoding::Bits
+        }
+    });
+
+    writer.write_all(MAGIC)?;
+    writer.write_all(&[VERSION, encoding as u8])?;
+    writer.write_all(&width.to_le_bytes())?;
+    writer.write_all(&height.to_le_bytes())?;
+    writer.write_all(&grid.seed.to_le_bytes())?;
+
+    match encoding {
+        Encoding::Bits => {
+            let mut byte = 0u8;
+
+            for pos in 0..grid.len() {
+                if grid.is_wall(pos) {
+                    byte |= 1 << (pos % 8);
+                }
+
+                if pos % 8 == 7 {
+                    writer.write_all(&[byte])?;
+                    byte = 0;
+                }
+            }
+
+            if !grid.len().is_multiple_of(8) {
+                writer.write_all(&[byte])?;
+            }
+        }
+        Encoding::RunLength => {
+            for run in grid.runs() {
+                write_varint(writer, run)?;
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// read a grid back, nothing the size of the map is allocated until the
+/// tiles are read and found to fill it
+pub fn read_compact<R: Read>(reader: &mut R) -> io::Result<BitGrid> {
+    let mut header = [0; 22];
+    reader.read_exact(&mut header)?;
+
+    if &header[0..4] != MAGIC {
+        return Err(invalid("not a compact map"));
+    }
+
+    if header[4] != VERSION {
+        return Err(invalid(format!("unknown version {}", header[4])));
+    }
+
+    let encoding = match header[5] {
+        0 => Encoding::Bits,
+        1 => Encoding::RunLength,
+        other => return Err(invalid(format!("unknown encoding {}", other))),
+    };
+
+    let width = u32::from_le_bytes(header[6..10].try_into().unwrap()) as usize;
+    let height =
+        u32::from_le_bytes(header[10..14].try_into().unwrap()) as usize;
+    let seed = u64::from_le_bytes(header[14..22].try_into().unwrap());
+
+    let len = width
+        .checked_mul(height)
+        .filter(|len| *len <= MAX_TILES)
+        .ok_or_else(|| invalid("the map is too big"))?;
+
+    let cut_short = |err: io::Error| match err.kind() {
+        io::ErrorKind::UnexpectedEof => {
+            invalid("the tiles stop before the map ends")
+        }
+        _ => err,
+    };
+
+    let mut grid = match encoding {
+        Encoding::Bits => {
+            let mut bytes = vec![];
+            reader
+                .by_ref()
+                .take(len.div_ceil(8) as u64)
+                .read_to_end(&mut bytes)?;
+
+            if bytes.len() != len.div_ceil(8) {
+                return Err(invalid("the tiles stop before the map ends"));
+            }
+
+            let mut grid = BitGrid::new(width, height);
+
+            for pos in 0..len {
+                grid.set_wall(pos, bytes[pos / 8] & (1 << (pos % 8)) != 0);
+            }
+
+            grid
+        }
+        Encoding::RunLength => {
+            let mut runs = vec![];
+            let mut total = 0;
+
+            while total < len {
+                let run = read_varint(reader).map_err(cut_short)? as usize;
+
+                total = total
+                    .checked_add(run)
+                    .filter(|total| *total <= len)
+                    .ok_or_else(|| {
+                        invalid("runs go past the end of the map")
+                    })?;
+
+                runs.push(run);
+            }
+
+            let mut grid = BitGrid::new(width, height);
+            let mut pos = 0;
+
+            for (i, run) in runs.into_iter().enumerate() {
+                for tile in pos..pos + run {
+                    grid.set_wall(tile, i % 2 == 0);
+                }
+
+                pos += run;
+            }
+
+            grid
+        }
+    };
+
+    grid.seed = seed;
+
+    Ok(grid)
+}
+
+pub fn save_compact<P: AsRef<Path>>(path: P, grid: &BitGrid) -> io::Result<()> {
+    let mut writer = BufWriter::new(File::create(path)?);
+
+    write_compact(&mut writer, grid, None)?;
+
+    writer.flush()
+}
+
+pub fn load_compact<P: AsRef<Path>>(path: P) -> io::Result<BitGrid> {
+    read_compact(&mut BufReader::new(File::open(path)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::get_map;
+
+    fn round_trip(grid: &BitGrid, encoding: Option<Encoding>) -> BitGrid {
+        let mut out = vec![];
+        write_compact(&mut out, grid, encoding).unwrap();
+
+        read_compact(&mut out.as_slice()).unwrap()
+    }
+
+    #[test]
+    fn round_trips() {
+        let grid = BitGrid::from_map(&get_map(37, 21, 5));
+
+        for encoding in [None, Some(Encoding::Bits), Some(Encoding::RunLength)]
+        {
+            assert_eq!(round_trip(&grid, encoding), grid);
+        }
+
+        assert_eq!(
+            grid.to_map().map,
+            BitGrid::from_map(&grid.to_map()).to_map().map
+        );
+    }
+
+    #[test]
+    fn header_is_checked_against_the_tiles() {
+        let header = |encoding: Encoding, width: u32, height: u32| {
+            let mut file = MAGIC.to_vec();
+            file.extend([VERSION, encoding as u8]);
+            file.extend(width.to_le_bytes());
+            file.extend(height.to_le_bytes());
+            file.extend(0u64.to_le_bytes());
+            file
+        };
+
+        let mut runs = header(Encoding::RunLength, 1000, 1000);
+        write_varint(&mut runs, 999_999).unwrap();
+
+        let mut past_the_end = header(Encoding::RunLength, 1000, 1000);
+        write_varint(&mut past_the_end, 1_000_001).unwrap();
+
+        for file in [
+            header(Encoding::Bits, u32::MAX, u32::MAX),
+            header(Encoding::RunLength, u32::MAX, u32::MAX),
+            header(Encoding::Bits, 1000, 1000),
+            runs,
+            past_the_end,
+        ] {
+            let err = read_compact(&mut file.as_slice()).unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn runs_start_with_walls() {
+        let grid = |glyphs: &str| {
+            let map = glyphs
+                .chars()
+                .map(|glyph| Tile::from_glyph(glyph).unwrap())
+                .collect();
+
+            BitGrid::from_map(&MapData::from_tiles(glyphs.len(), 1, map))
+        };
+
+        let runs = |glyphs| grid(glyphs).runs().collect::<Vec<u64>>();
+
+        assert_eq!(runs("##..#"), [2, 2, 1]);
+        assert_eq!(runs("..##"), [0, 2, 2]);
+        assert_eq!(runs("###"), [3]);
+        assert_eq!(runs(""), [0]);
+    }
+
+    #[test]
+    fn sides_past_32_bits_are_refused() {
+        let grid = BitGrid {
+            width: 1 << 32,
+            height: 0,
+            seed: 0,
+            words: vec![],
+        };
+
+        let err = write_compact(&mut io::sink(), &grid, None).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
@@ -0,0 +1,145 @@
+// grid coordinates
+//
+// `MapPos` is the flat index the searches use for a tile, `Coord` is the same
+// tile as x and y with y growing down the map. the conversions between them
+// check the grid size so stepping off an edge never wraps onto the next row
+
+use std::{
+    fmt,
+    ops::{Add, Sub},
+};
+
+use crate::map::MapPos;
+
+/// a tile position, or the offset between two, signed so steps off the map
+/// can be represented and then rejected
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Coord {
+    pub x: isize,
+    pub y: isize,
+}
+
+impl Coord {
+    pub const fn new(x: isize, y: isize) -> Self {
+        Self { x, y }
+    }
+
+    pub fn from_index(index: MapPos, width: usize) -> Self {
+        Self::new((index % width) as isize, (index / width) as isize)
+    }
+
+    /// the flat index, `None` when the coordinate is off a `width` by
+    /// `height` grid
+    pub fn to_index(self, width: usize, height: usize) -> Option<MapPos> {
+        self.in_bounds(width, height)
+            .then(|| self.x as usize + self.y as usize * width)
+    }
+
+    pub fn in_bounds(self, width: usize, height: usize) -> bool {
+        self.x >= 0
+            && self.y >= 0
+            && self.x < width as isize
+            && self.y < height as isize
+    }
+
+    /// the neighbor one step in `dir`
+    pub fn step(self, dir: Direction) -> Self {
+        self + dir.offset()
+    }
+
+    pub fn abs(self) -> Self {
+        Self::new(self.x.abs(), self.y.abs())
+    }
+
+    pub fn manhattan(self, other: Self) -> isize {
+        let delta = (self - other).abs();
+
+        delta.x + delta.y
+    }
+
+    pub fn chebyshev(self, other: Self) -> isize {
+        let delta = (self - other).abs();
+
+        delta.x.max(delta.y)
+    }
+}
+
+impl Add for Coord {
+    type Output = Self;
+
+    fn add(self, other: Self) -> Self {
+        Self::new(self.x + other.x, self.y + other.y)
+    }
+}
+
+impl Sub for Coord {
+    type Output = Self;
+
+    fn sub(self, other: Self) -> Self {
+        Self::new(self.x - other.x, self.y - other.y)
+    }
+}
+
+/// written the way the command line takes points, `x,y`
+impl fmt::Display for Coord {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{},{}", self.x, self.y)
+    }
+}
+
+/// the eight steps to a neighboring tile
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Direction {
+    North,
+    NorthEast,
+    East,
+    SouthEast,
+    South,
+    SouthWest,
+    West,
+    NorthWest,
+}
+
+impl Direction {
+    /// every direction in the order the searches try them, which decides
+    /// between paths that cost the same
+    pub const ALL: [Direction; 8] = [
+        Direction::NorthWest,
+        Direction::West,
+        Direction::SouthWest,
+        Direction::North,
+        Direction::South,
+        Direction::NorthEast,
+        Direction::East,
+        Direction::SouthEast,
+    ];
+
+    pub fn offset(self) -> Coord {
+        match self {
+            Direction::North => Coord::new(0, -1),
+            Direction::NorthEast => Coord::new(1, -1),
+            Direction::East => Coord::new(1, 0),
+            Direction::SouthEast => Coord::new(1, 1),
+            Direction::South => Coord::new(0, 1),
+            Direction::SouthWest => Coord::new(-1, 1),
+            Direction::West => Coord::new(-1, 0),
+            Direction::NorthWest => Coord::new(-1, -1),
+        }
+    }
+
+    /// the direction of a one tile step, `None` for anything further or no
+    /// step at all
+    pub fn from_offset(offset: Coord) -> Option<Self> {
+        Direction::ALL
+            .into_iter()
+            .find(|dir| dir.offset() == offset)
+    }
+
+    pub fn is_diagonal(self) -> bool {
+        let offset = self.offset();
+
+        offset.x != 0 && offset.y != 0
+    }
+}
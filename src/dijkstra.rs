@@ -1,28 +1,24 @@
 use std::{
     cmp::Ordering,
-    collections::{BinaryHeap, HashMap},
+    collections::{BinaryHeap, HashMap, HashSet},
+    hash::Hash,
 };
 
-use crate::map::{MapData, Tile};
-
-const NEIGHBORS: &[(isize, isize); 8] = &[
-    (-1, -1),
-    (-1, 0),
-    (-1, 1),
-    (0, -1),
-    (0, 1),
-    (1, 1),
-    (1, 0),
-    (1, 1),
-];
+use crate::{
+    graph::SearchSpace,
+    map::Tile,
+    movement::{StepCosts, TerrainCosts},
+    render::SearchTrace,
+    replay::Replay,
+};
 
 #[derive(Copy, Clone, Eq, PartialEq)]
-struct State {
+struct State<N> {
     cost: usize,
-    position: usize,
+    position: N,
 }
 
-impl Ord for State {
+impl<N: Ord> Ord for State<N> {
     fn cmp(&self, other: &Self) -> Ordering {
         other
             .cost
@@ -31,69 +27,111 @@ impl Ord for State {
     }
 }
 
-impl PartialOrd for State {
+impl<N: Ord> PartialOrd for State<N> {
     fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
         Some(self.cmp(other))
     }
 }
 
-pub struct Dijkstra {
-    frontier: BinaryHeap<State>,
-    came_from: HashMap<usize, usize>,
-    cost_so_far: HashMap<usize, usize>,
+/// the step costs dijkstra has always used on grids, walls are cheaper to
+/// cross than they are for a*
+pub fn grid_costs() -> StepCosts {
+    StepCosts::default()
+        .with_terrain(TerrainCosts::default().with(Tile::Wall, Some(10)))
 }
 
-impl Dijkstra {
+/// dijkstra over any search space, grids by default, the heuristic of the
+/// space is never asked for
+pub struct Dijkstra<N = usize> {
+    frontier: BinaryHeap<State<N>>,
+    came_from: HashMap<N, N>,
+    cost_so_far: HashMap<N, usize>,
+    closed: HashSet<N>,
+    replay: Option<Replay<N>>,
+}
+
+impl<N: Copy + Eq + Hash + Ord> Default for Dijkstra<N> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<N: Copy + Eq + Hash + Ord> Dijkstra<N> {
     pub fn new() -> Self {
         Self {
             frontier: BinaryHeap::new(),
             came_from: HashMap::new(),
             cost_so_far: HashMap::new(),
+            closed: HashSet::new(),
+            replay: None,
         }
     }
 
-    pub fn run(
+    /// a Dijkstra that keeps a replay of every expansion it makes
+    pub fn recording() -> Self {
+        Self {
+            replay: Some(Replay::default()),
+            ..Self::new()
+        }
+    }
+
+    pub fn replay(&self) -> Option<&Replay<N>> {
+        self.replay.as_ref()
+    }
+
+    /// the cost of the best path found to `node` in the last run
+    pub fn cost(&self, node: N) -> Option<usize> {
+        self.cost_so_far.get(&node).copied()
+    }
+
+    /// the nodes the last run expanded
+    pub fn closed(&self) -> &HashSet<N> {
+        &self.closed
+    }
+
+    /// search from `start` until `end` is expanded, the returned map leads
+    /// each reached node back towards `start`
+    pub fn run<S: SearchSpace<Node = N>>(
         &mut self,
-        map_data: &MapData,
-        start: usize,
-        end: usize,
-    ) -> HashMap<usize, usize> {
+        space: &S,
+        start: N,
+        end: N,
+    ) -> HashMap<N, N> {
+        self.frontier.clear();
+        self.came_from.clear();
+        self.cost_so_far.clear();
+        self.closed.clear();
+
+        if let Some(replay) = self.replay.as_mut() {
+            *replay = Replay::default();
+        }
+
         let start_state = State {
             cost: 0,
             position: start,
         };
 
         self.frontier.push(start_state);
-        self.came_from.insert(start, 0);
         self.cost_so_far.insert(start, 0);
 
-        while !self.frontier.is_empty() {
-            let current = self.frontier.pop().unwrap();
+        while let Some(current) = self.frontier.pop() {
+            // costs are never negative so the first time a node comes off
+            // the heap is the cheapest, later entries for it are stale
+            if !self.closed.insert(current.position) {
+                continue;
+            }
+
+            if let Some(replay) = self.replay.as_mut() {
+                replay.expand(current.position);
+            }
 
             if current.position == end {
                 break;
             }
 
-            for (x, y) in NEIGHBORS {
-                let c_x = current.position % map_data.map_width;
-                let c_y = current.position / map_data.map_width;
-
-                let n_x = c_x as isize + x;
-                let n_y = c_y as isize + y;
-
-                let n_index = n_x + (n_y * map_data.map_width as isize);
-
-                if n_index < 0 || n_index >= map_data.map.len() as isize {
-                    continue;
-                }
-
-                let index = n_index as usize;
-
-                let tile = &map_data.map[index];
-
-                let tile_cost = if *tile == Tile::Wall { 10 } else { 1 };
-
-                let new_cost = self.cost_so_far[&current.position] + tile_cost;
+            for (index, tile_cost) in space.neighbors(current.position) {
+                let new_cost =
+                    self.cost_so_far[&current.position] + tile_cost as usize;
 
                 if !self.cost_so_far.contains_key(&index)
                     || new_cost < self.cost_so_far[&index]
@@ -104,6 +142,10 @@ impl Dijkstra {
                         position: index,
                     };
                     self.frontier.push(new_state);
+
+                    if let Some(replay) = self.replay.as_mut() {
+                        replay.push(index);
+                    }
                     self.came_from.insert(index, current.position);
                 }
             }
@@ -112,3 +154,61 @@ impl Dijkstra {
         self.came_from.clone()
     }
 }
+
+impl Dijkstra {
+    /// what the last run touched, for the renderers
+    pub fn trace(&self) -> SearchTrace {
+        let open = self
+            .frontier
+            .iter()
+            .map(|state| state.position)
+            .filter(|pos| !self.closed.contains(pos))
+            .collect();
+
+        let cost = self
+            .cost_so_far
+            .iter()
+            .map(|(pos, cost)| (*pos, *cost as isize))
+            .collect();
+
+        SearchTrace {
+            closed: self.closed.clone(),
+            open,
+            cost,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::Graph;
+
+    #[test]
+    fn reused_runs_skip_stale_entries() {
+        // b is pushed at 5 then again at 2 through a, the 5 is stale
+        let mut graph = Graph::new();
+        graph.add_edge('s', 'a', 1);
+        graph.add_edge('s', 'b', 5);
+        graph.add_edge('a', 'b', 1);
+        graph.add_edge('b', 'c', 1);
+
+        let mut dijkstra = Dijkstra::recording();
+
+        for _ in 0..2 {
+            // nothing reaches z so everything is expanded
+            dijkstra.run(&graph, 's', 'z');
+
+            let expanded: Vec<char> = dijkstra
+                .replay()
+                .unwrap()
+                .steps
+                .iter()
+                .map(|step| step.expanded)
+                .collect();
+
+            assert_eq!(expanded, ['s', 'a', 'b', 'c']);
+            assert_eq!(dijkstra.cost('b'), Some(2));
+        }
+    }
+}
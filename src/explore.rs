@@ -0,0 +1,452 @@
+// interactive explorer
+//
+// a terminal ui over the generated map, the search is rerun after every
+// change so the path and the explored tiles always match what is on screen.
+// raw mode is set up through `stty` to keep this free of dependencies
+
+use std::{
+    fs::File,
+    io::{self, IsTerminal, Read, Write},
+    process::{Command, Stdio},
+};
+
+use crate::{
+    astar::Heuristic,
+    coord::{Coord, Direction},
+    map::{Grid, MapData, MapPos, Tile},
+    movement::MovementProfile,
+    regions::Regions,
+    render::{render_view, RenderOptions, View},
+    room_graph::RoomGraph,
+    search::{search_in, search_terrain, Algorithm, Found, SearchOptions},
+};
+
+const HELP: &str = "\
+arrows/hjkl move  s start  g goal  w wall  r terrain  a algorithm  \
+e heuristic  p profile  z size  t explored  c heat  q quit";
+
+// the status line and the help under the map
+const STATUS_LINES: usize = 2;
+
+// what ctrl-c sends once the terminal stops turning it into a signal
+const CTRL_C: char = '\x03';
+
+const HEURISTICS: &[Heuristic] = &[
+    Heuristic::Manhattan,
+    Heuristic::Chebyshev,
+    Heuristic::Octile,
+    Heuristic::Euclidean,
+    Heuristic::Zero,
+];
+
+enum Key {
+    Up,
+    Down,
+    Left,
+    Right,
+    Char(char),
+}
+
+// puts the terminal back the way it was when dropped
+struct RawMode {
+    saved: String,
+}
+
+impl RawMode {
+    fn enable() -> io::Result<Self> {
+        let saved = stty(&["-g"])?;
+
+        // -isig so ctrl-c comes in as a key and the terminal still gets put
+        // back, min 0 time 1 so a read gives up after a tenth of a second
+        // and a lone escape is not mistaken for the start of an arrow key
+        stty(&["-icanon", "-echo", "-isig", "min", "0", "time", "1"])?;
+
+        // alternate screen and hide the cursor while drawing
+        print!("\x1b[?1049h\x1b[?25l");
+        io::stdout().flush()?;
+
+        Ok(Self {
+            saved: saved.trim().to_string(),
+        })
+    }
+}
+
+impl Drop for RawMode {
+    fn drop(&mut self) {
+        print!("\x1b[?25h\x1b[?1049l");
+        let _ = io::stdout().flush();
+
+        let _ = stty(&[&self.saved]);
+    }
+}
+
+// rows and columns, `None` when stty can not tell
+fn terminal_size() -> Option<(usize, usize)> {
+    let size = stty(&["size"]).ok()?;
+    let (rows, columns) = size.trim().split_once(' ')?;
+
+    Some((rows.parse().ok()?, columns.parse().ok()?))
+}
+
+fn stty(args: &[&str]) -> io::Result<String> {
+    let output = Command::new("stty")
+        .args(args)
+        .stdin(Stdio::from(File::open("/dev/tty")?))
+        .output()?;
+
+    if !output.status.success() {
+        return Err(io::Error::other("stty failed"));
+    }
+
+    Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+pub struct Explorer {
+    map_data: MapData,
+    cursor: Coord,
+    start: Option<MapPos>,
+    goal: Option<MapPos>,
+    algorithm: Algorithm,
+    options: SearchOptions,
+    /// kept up to date with every edit so searches between tiles that are
+    /// walled off from each other end at once
+    regions: Regions,
+    /// built the first time rooms searches and thrown away on every edit
+    rooms: Option<RoomGraph>,
+    show_explored: bool,
+    show_heat: bool,
+    found: Option<Found>,
+}
+
+impl Explorer {
+    pub fn new(
+        map_data: MapData,
+        algorithm: Algorithm,
+        options: SearchOptions,
+    ) -> Self {
+        let cursor = Coord::new(
+            map_data.map_width as isize / 2,
+            map_data.map_height as isize / 2,
+        );
+
+        let regions =
+            Regions::new(&map_data, search_terrain(algorithm, &options));
+
+        Self {
+            map_data,
+            cursor,
+            start: None,
+            goal: None,
+            algorithm,
+            options,
+            regions,
+            rooms: None,
+            show_explored: true,
+            show_heat: false,
+            found: None,
+        }
+    }
+
+    pub fn run(&mut self) -> io::Result<()> {
+        if !io::stdin().is_terminal() {
+            return Err(io::Error::other(
+                "explore needs an interactive terminal",
+            ));
+        }
+
+        let _raw = RawMode::enable()?;
+
+        let mut stdin = io::stdin().lock();
+
+        self.draw()?;
+
+        loop {
+            // reads time out so nothing came in is not the end of input
+            match read_key(&mut stdin)? {
+                Some(Key::Char('q')) | Some(Key::Char(CTRL_C)) => return Ok(()),
+                Some(key) => self.handle(key),
+                None => continue,
+            }
+
+            self.draw()?;
+        }
+    }
+
+    fn cursor_pos(&self) -> MapPos {
+        self.map_data
+            .index(self.cursor)
+            .expect("the cursor stays on the map")
+    }
+
+    // move the cursor unless that takes it off the map
+    fn move_cursor(&mut self, dir: Direction) {
+        let next = self.cursor.step(dir);
+
+        if self.map_data.index(next).is_some() {
+            self.cursor = next;
+        }
+    }
+
+    fn handle(&mut self, key: Key) {
+        match key {
+            Key::Up | Key::Char('k') => self.move_cursor(Direction::North),
+            Key::Down | Key::Char('j') => self.move_cursor(Direction::South),
+            Key::Left | Key::Char('h') => self.move_cursor(Direction::West),
+            Key::Right | Key::Char('l') => self.move_cursor(Direction::East),
+            Key::Char('s') => {
+                self.start = Some(self.cursor_pos());
+                self.search();
+            }
+            Key::Char('g') => {
+                self.goal = Some(self.cursor_pos());
+                self.search();
+            }
+            Key::Char('w') | Key::Char(' ') => {
+                let pos = self.cursor_pos();
+
+                self.map_data.map[pos] = match self.map_data.map[pos] {
+                    Tile::Wall => Tile::Floor,
+                    _ => Tile::Wall,
+                };
+
+                self.regions.update(&self.map_data, pos);
+                self.rooms = None;
+                self.search();
+            }
+            Key::Char('r') => {
+                let pos = self.cursor_pos();
+                let next = (self.map_data.map[pos] as usize + 1) % Tile::COUNT;
+
+                self.map_data.map[pos] = Tile::ALL[next];
+
+                self.regions.update(&self.map_data, pos);
+                self.rooms = None;
+                self.search();
+            }
+            Key::Char('a') => {
+                let current = Algorithm::ALL
+                    .iter()
+                    .position(|algorithm| *algorithm == self.algorithm)
+                    .unwrap_or(0);
+
+                self.algorithm =
+                    Algorithm::ALL[(current + 1) % Algorithm::ALL.len()];
+                self.search();
+            }
+            Key::Char('e') => {
+                let current = HEURISTICS
+                    .iter()
+                    .position(|heuristic| *heuristic == self.options.heuristic)
+                    .unwrap_or(0);
+
+                self.options.heuristic =
+                    HEURISTICS[(current + 1) % HEURISTICS.len()];
+                self.search();
+            }
+            Key::Char('p') => {
+                let names = MovementProfile::NAMES;
+                let current = names
+                    .iter()
+                    .position(|name| *name == self.profile_name())
+                    .map_or(0, |i| i + 1);
+
+                self.options.profile =
+                    MovementProfile::from_name(names[current % names.len()]);
+                self.search();
+            }
+            Key::Char('z') => {
+                self.options.agent_size = self.options.agent_size % 3 + 1;
+                self.search();
+            }
+            Key::Char('t') => self.show_explored = !self.show_explored,
+            Key::Char('c') => self.show_heat = !self.show_heat,
+            Key::Char(_) => {}
+        }
+    }
+
+    // the name of the profile in use, profiles from elsewhere are custom
+    fn profile_name(&self) -> &'static str {
+        let Some(profile) = self.options.profile else {
+            return "default";
+        };
+
+        MovementProfile::NAMES
+            .iter()
+            .find(|name| MovementProfile::from_name(name) == Some(profile))
+            .copied()
+            .unwrap_or("custom")
+    }
+
+    fn search(&mut self) {
+        // a new algorithm or profile can move over other terrain
+        let terrain = search_terrain(self.algorithm, &self.options);
+
+        if self.regions.terrain() != terrain {
+            self.regions = Regions::new(&self.map_data, terrain);
+        }
+
+        if self.algorithm == Algorithm::Rooms && self.rooms.is_none() {
+            self.rooms = Some(RoomGraph::new(&self.map_data));
+        }
+
+        self.found = match (self.start, self.goal) {
+            (Some(start), Some(goal)) => Some(search_in(
+                self.algorithm,
+                &self.map_data,
+                &self.regions,
+                self.rooms.as_ref(),
+                start,
+                goal,
+                &self.options,
+            )),
+            _ => None,
+        };
+    }
+
+    fn draw(&self) -> io::Result<()> {
+        let empty = vec![];
+
+        let (path, trace) = match &self.found {
+            Some(found) => (&found.path, Some(&found.trace)),
+            None => (&empty, None),
+        };
+
+        let options = RenderOptions {
+            closed: self.show_explored,
+            open: self.show_explored,
+            heat: self.show_heat,
+            ..RenderOptions::default()
+        };
+
+        // keep the cursor in sight on maps bigger than the terminal
+        let (rows, columns) = terminal_size().unwrap_or((
+            self.map_data.map_height + STATUS_LINES,
+            self.map_data.map_width,
+        ));
+        let view = View::around(
+            &self.map_data,
+            (self.cursor.x as usize, self.cursor.y as usize),
+            columns,
+            rows.saturating_sub(STATUS_LINES),
+        );
+
+        let mut out = String::from("\x1b[H\x1b[2J");
+
+        for line in
+            render_view(&self.map_data, path, trace, options, view).lines()
+        {
+            out.push_str(line);
+            out.push_str("\r\n");
+        }
+
+        let status = match &self.found {
+            Some(found) if found.path.is_empty() => "no path".to_string(),
+            Some(found) => format!(
+                "steps {} expanded {} in {:?}",
+                found.path.len() - 1,
+                found.trace.closed.len(),
+                found.elapsed
+            ),
+            None => "place a start and a goal".to_string(),
+        };
+
+        let status = format!(
+            "{} {:?} {} {}x{} | cursor {} | {}",
+            self.algorithm.name(),
+            self.options.heuristic,
+            self.profile_name(),
+            self.options.agent_size,
+            self.options.agent_size,
+            self.cursor,
+            status,
+        );
+
+        // cut to the width so a wrapped line does not scroll the map away
+        for (i, line) in [status.as_str(), HELP].iter().enumerate() {
+            if i > 0 {
+                out.push_str("\r\n");
+            }
+
+            out.extend(line.chars().take(columns));
+        }
+
+        // draw the cursor over the map, the terminal counts from 1
+        let pos = self.cursor_pos();
+        let glyph = match (self.start, self.goal) {
+            (Some(start), _) if start == pos => 'S',
+            (_, Some(goal)) if goal == pos => 'E',
+            _ if self.map_data.map[pos] == Tile::Floor => ' ',
+            _ => self.map_data.map[pos].glyph(),
+        };
+
+        out.push_str(&format!(
+            "\x1b[{};{}H\x1b[7m{}\x1b[0m",
+            self.cursor.y as usize - view.y + 1,
+            self.cursor.x as usize - view.x + 1,
+            glyph
+        ));
+
+        let mut stdout = io::stdout().lock();
+        stdout.write_all(out.as_bytes())?;
+        stdout.flush()
+    }
+}
+
+fn read_key<R: Read>(input: &mut R) -> io::Result<Option<Key>> {
+    let mut byte = [0; 1];
+
+    // nothing was typed before the read timed out
+    if input.read(&mut byte)? == 0 {
+        return Ok(None);
+    }
+
+    if byte[0] != 0x1b {
+        return Ok(Some(Key::Char(byte[0] as char)));
+    }
+
+    // arrow keys come in as escape [ and a letter, a read that times out
+    // before the rest shows up was a lone escape
+    let mut seq = [0; 2];
+
+    for byte in seq.iter_mut() {
+        if input.read(std::slice::from_mut(byte))? == 0 {
+            return Ok(Some(Key::Char('\x1b')));
+        }
+    }
+
+    let key = match seq {
+        [b'[', b'A'] => Key::Up,
+        [b'[', b'B'] => Key::Down,
+        [b'[', b'C'] => Key::Right,
+        [b'[', b'D'] => Key::Left,
+        _ => Key::Char('\0'),
+    };
+
+    Ok(Some(key))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keys(mut input: &[u8]) -> Vec<Option<char>> {
+        let mut keys = vec![];
+
+        while !input.is_empty() {
+            keys.push(match read_key(&mut input).unwrap() {
+                Some(Key::Char(c)) => Some(c),
+                Some(_) => None,
+                None => break,
+            });
+        }
+
+        keys
+    }
+
+    #[test]
+    fn lone_escape_does_not_wait_for_more() {
+        assert_eq!(keys(b"\x1b"), [Some('\x1b')]);
+        assert_eq!(keys(b"\x1b["), [Some('\x1b')]);
+        assert_eq!(keys(b"\x1b[Aq"), [None, Some('q')]);
+    }
+}
@@ -0,0 +1,171 @@
+// generator settings
+//
+// how many rooms the room generators try to place, how big they get, what
+// shape they are and how the hallways between them are dug. a config is
+// checked against the map size before anything is carved so a map that can
+// not hold it gets an error instead of a panic halfway through
+
+use crate::rng::Pcg32;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum RoomShape {
+    Rect,
+    /// the ellipse that fits the room's rect
+    Circle,
+    /// a coin flip between the two for every room
+    Mixed,
+}
+
+impl RoomShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "rect" => Some(RoomShape::Rect),
+            "circle" => Some(RoomShape::Circle),
+            "mixed" => Some(RoomShape::Mixed),
+            _ => None,
+        }
+    }
+
+    /// the shape of the next room, `Mixed` picks one of the others
+    pub fn pick(self, rng: &mut Pcg32) -> Self {
+        match self {
+            RoomShape::Mixed if rng.chance(0.5) => RoomShape::Circle,
+            RoomShape::Mixed => RoomShape::Rect,
+            shape => shape,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum CorridorShape {
+    /// across then down or down then across, whichever the coin says
+    LShaped,
+    /// as close to a straight line between the room centers as a hallway
+    /// without diagonal steps gets
+    Straight,
+}
+
+impl CorridorShape {
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "l" => Some(CorridorShape::LShaped),
+            "straight" => Some(CorridorShape::Straight),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct GenConfig {
+    /// how many rooms are tried, ones that overlap are thrown away
+    pub max_rooms: usize,
+    pub min_room_size: usize,
+    pub max_room_size: usize,
+    /// hallways added on top of the ones every room needs, so there is more
+    /// than one way around
+    pub loops: usize,
+    pub room_shape: RoomShape,
+    pub corridor_shape: CorridorShape,
+    /// how many tiles across hallways are
+    pub corridor_width: usize,
+}
+
+impl Default for GenConfig {
+    fn default() -> Self {
+        Self {
+            max_rooms: 30,
+            min_room_size: 4,
+            max_room_size: 8,
+            loops: 2,
+            room_shape: RoomShape::Rect,
+            corridor_shape: CorridorShape::LShaped,
+            corridor_width: 1,
+        }
+    }
+}
+
+impl GenConfig {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn with_max_rooms(mut self, max_rooms: usize) -> Self {
+        self.max_rooms = max_rooms;
+        self
+    }
+
+    /// rooms are between `min` and `max` tiles across, walls not counted
+    pub fn with_room_size(mut self, min: usize, max: usize) -> Self {
+        self.min_room_size = min;
+        self.max_room_size = max;
+        self
+    }
+
+    pub fn with_loops(mut self, loops: usize) -> Self {
+        self.loops = loops;
+        self
+    }
+
+    pub fn with_room_shape(mut self, shape: RoomShape) -> Self {
+        self.room_shape = shape;
+        self
+    }
+
+    pub fn with_corridor_shape(mut self, shape: CorridorShape) -> Self {
+        self.corridor_shape = shape;
+        self
+    }
+
+    pub fn with_corridor_width(mut self, width: usize) -> Self {
+        self.corridor_width = width;
+        self
+    }
+
+    /// whether the config can make a `width` by `height` map, the error
+    /// says what is wrong when it can not
+    pub fn validate(&self, width: usize, height: usize) -> Result<(), String> {
+        if self.max_rooms == 0 {
+            return Err("max_rooms must be at least 1".to_string());
+        }
+
+        if self.min_room_size == 0 {
+            return Err("min_room_size must be at least 1".to_string());
+        }
+
+        if self.min_room_size > self.max_room_size {
+            return Err(format!(
+                "max_room_size must be at least min_room_size {}, got {}",
+                self.min_room_size, self.max_room_size
+            ));
+        }
+
+        if self.corridor_width == 0 {
+            return Err("corridor_width must be at least 1".to_string());
+        }
+
+        // a room of size n covers n + 1 tiles
+        if self.corridor_width > self.min_room_size + 1 {
+            return Err(format!(
+                "corridor_width must be at most min_room_size + 1, {}, got {}",
+                self.min_room_size + 1,
+                self.corridor_width
+            ));
+        }
+
+        // the room, the wall around the map and at least one spot to put it
+        let needed = self.max_room_size + 3;
+
+        if width < needed || height < needed {
+            return Err(format!(
+                "the map must be at least max_room_size + 3, {} by {}, got \
+                 {} by {}",
+                needed, needed, width, height
+            ));
+        }
+
+        Ok(())
+    }
+}
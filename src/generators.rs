@@ -0,0 +1,730 @@
+// map generators
+//
+// different ways of laying out a map so the searches can be tried on more
+// than one kind of topology, rooms and corridors, caves, winding tunnels,
+// perfect mazes and open terrain. every generator is seeded, the same seed
+// and size always give the same map. the ones that place rooms take their
+// sizes and shapes from a `GenConfig`
+
+use crate::{
+    coord::{Coord, Direction},
+    gen_config::GenConfig,
+    graph::{reachable, GridSpace},
+    map::{Grid, MapData, MapRect, Tile},
+    movement::MovementProfile,
+    rng::Pcg32,
+};
+
+pub trait MapGenerator {
+    /// what the command line calls the generator
+    fn name(&self) -> &'static str;
+
+    /// the map for `seed`, an error when the generator can not make one
+    /// that size
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Result<MapData, String>;
+}
+
+pub const NAMES: &[&str] = &[
+    "rooms",
+    "bsp",
+    "caves",
+    "drunkard",
+    "backtracker",
+    "prim",
+    "kruskal",
+    "terrain",
+];
+
+/// the generator called `name`, the room generators use `config` and the
+/// rest their default settings
+pub fn from_name(
+    name: &str,
+    config: GenConfig,
+) -> Option<Box<dyn MapGenerator>> {
+    let generator: Box<dyn MapGenerator> = match name {
+        "rooms" => Box::new(Rooms { config }),
+        "bsp" => Box::new(Bsp {
+            config,
+            ..Bsp::default()
+        }),
+        "caves" => Box::new(Caves::default()),
+        "drunkard" => Box::new(Drunkard::default()),
+        "backtracker" => Box::new(Maze::new(MazeKind::Backtracker)),
+        "prim" => Box::new(Maze::new(MazeKind::Prim)),
+        "kruskal" => Box::new(Maze::new(MazeKind::Kruskal)),
+        "terrain" => Box::new(Terrain::default()),
+        _ => return None,
+    };
+
+    Some(generator)
+}
+
+// an all wall map to carve into
+fn solid(width: usize, height: usize, seed: u64) -> MapData {
+    MapData::new(width, height, seed)
+}
+
+/// random rooms joined by a spanning tree of hallways, `MapData::gen`
+#[derive(Default)]
+pub struct Rooms {
+    pub config: GenConfig,
+}
+
+impl MapGenerator for Rooms {
+    fn name(&self) -> &'static str {
+        "rooms"
+    }
+
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Result<MapData, String> {
+        let mut map_data = solid(width, height, seed).with_config(self.config);
+
+        map_data.gen()?;
+
+        Ok(map_data)
+    }
+}
+
+/// binary space partitioning, the map is split in two over and over and each
+/// leaf gets a room, sibling halves are joined by a hallway
+///
+/// the leaves decide how big rooms are, only the shapes and hallways come
+/// from the config
+pub struct Bsp {
+    /// the smallest a part can get before it stops splitting
+    pub min_leaf: usize,
+    pub config: GenConfig,
+}
+
+impl Default for Bsp {
+    fn default() -> Self {
+        Self {
+            min_leaf: 8,
+            config: GenConfig::default(),
+        }
+    }
+}
+
+impl Bsp {
+    // split the part at `x`, `y` of `width` by `height`, returns the index of
+    // a room inside it
+    fn split(
+        &self,
+        map_data: &mut MapData,
+        rng: &mut Pcg32,
+        (x, y, width, height): (usize, usize, usize, usize),
+    ) -> usize {
+        let min_leaf = self.min_leaf.max(5);
+        let can_split_x = width >= min_leaf * 2;
+        let can_split_y = height >= min_leaf * 2;
+
+        let split_x = match (can_split_x, can_split_y) {
+            (false, false) => {
+                // the room stays a tile away from the edge of its part
+                let room_width = rng.range(2..=width - 3);
+                let room_height = rng.range(2..=height - 3);
+
+                let room_x = rng.range(x + 1..=x + width - 2 - room_width);
+                let room_y = rng.range(y + 1..=y + height - 2 - room_height);
+
+                let shape = self.config.room_shape.pick(rng);
+
+                return map_data.add_room(
+                    MapRect::new(room_x, room_y, room_width, room_height),
+                    shape,
+                );
+            }
+            (true, false) => true,
+            (false, true) => false,
+            (true, true) => rng.chance(width as f64 / (width + height) as f64),
+        };
+
+        let (first, second) = if split_x {
+            let at = rng.range(min_leaf..=width - min_leaf);
+
+            ((x, y, at, height), (x + at, y, width - at, height))
+        } else {
+            let at = rng.range(min_leaf..=height - min_leaf);
+
+            ((x, y, width, at), (x, y + at, width, height - at))
+        };
+
+        let a = self.split(map_data, rng, first);
+        let b = self.split(map_data, rng, second);
+
+        map_data.join_rooms(rng, a, b);
+
+        a
+    }
+}
+
+impl MapGenerator for Bsp {
+    fn name(&self) -> &'static str {
+        "bsp"
+    }
+
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Result<MapData, String> {
+        let min_leaf = self.min_leaf.max(5);
+
+        if width < min_leaf || height < min_leaf {
+            return Err(format!(
+                "bsp needs a map at least {} by {}",
+                min_leaf, min_leaf
+            ));
+        }
+
+        let mut rng = Pcg32::new(seed);
+        let mut map_data = solid(width, height, seed);
+
+        self.split(&mut map_data, &mut rng, (0, 0, width, height));
+
+        Ok(map_data)
+    }
+}
+
+/// caves grown with a cellular automaton, random noise smoothed by turning
+/// tiles with mostly wall around them into wall. only the biggest cave is
+/// kept so the map stays connected
+pub struct Caves {
+    /// how much of the map starts out as wall
+    pub fill: f64,
+    /// how many times the noise is smoothed
+    pub steps: usize,
+}
+
+impl Default for Caves {
+    fn default() -> Self {
+        Self {
+            fill: 0.45,
+            steps: 4,
+        }
+    }
+}
+
+impl MapGenerator for Caves {
+    fn name(&self) -> &'static str {
+        "caves"
+    }
+
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Result<MapData, String> {
+        let mut rng = Pcg32::new(seed);
+        let mut map_data = solid(width, height, seed);
+
+        for pos in 0..map_data.len() {
+            let coord = map_data.coord(pos);
+            let edge = coord.x == 0
+                || coord.y == 0
+                || coord.x == width as isize - 1
+                || coord.y == height as isize - 1;
+
+            if !edge && !rng.chance(self.fill) {
+                map_data.map[pos] = Tile::Floor;
+            }
+        }
+
+        for _ in 0..self.steps {
+            let walls: Vec<usize> = (0..map_data.len())
+                .map(|pos| {
+                    let coord = map_data.coord(pos);
+
+                    // off the map counts as wall
+                    Direction::ALL
+                        .into_iter()
+                        .filter(|dir| {
+                            map_data.index(coord.step(*dir)).is_none_or(
+                                |next| map_data.map[next] == Tile::Wall,
+                            )
+                        })
+                        .count()
+                })
+                .collect();
+
+            for (pos, walls) in walls.into_iter().enumerate() {
+                map_data.map[pos] = match (map_data.map[pos], walls) {
+                    (_, 5..) => Tile::Wall,
+                    (Tile::Wall, 4) => Tile::Wall,
+                    _ => Tile::Floor,
+                };
+            }
+        }
+
+        keep_largest_region(&mut map_data);
+
+        Ok(map_data)
+    }
+}
+
+// wall over everything a walker can not get to from the biggest open region
+fn keep_largest_region(map_data: &mut MapData) {
+    let walker = MovementProfile::walker();
+    let mut seen = vec![false; map_data.len()];
+    let mut largest = vec![];
+
+    for pos in 0..map_data.len() {
+        if seen[pos] || !walker.can_enter(map_data.map[pos]) {
+            continue;
+        }
+
+        let space = GridSpace::new(&*map_data).with_profile(&walker);
+        let region: Vec<usize> = reachable(&space, pos).into_iter().collect();
+
+        for pos in &region {
+            seen[*pos] = true;
+        }
+
+        if region.len() > largest.len() {
+            largest = region;
+        }
+    }
+
+    let mut keep = vec![false; map_data.len()];
+    for pos in largest {
+        keep[pos] = true;
+    }
+
+    for (tile, keep) in map_data.map.iter_mut().zip(keep) {
+        if !keep {
+            *tile = Tile::Wall;
+        }
+    }
+}
+
+/// a drunkard's walk, one walker staggers about the map digging as it goes
+/// until enough of it is open
+pub struct Drunkard {
+    /// how much of the map to dig out
+    pub coverage: f64,
+}
+
+impl Default for Drunkard {
+    fn default() -> Self {
+        Self { coverage: 0.4 }
+    }
+}
+
+impl MapGenerator for Drunkard {
+    fn name(&self) -> &'static str {
+        "drunkard"
+    }
+
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Result<MapData, String> {
+        if width < 3 || height < 3 {
+            return Err("the drunkard needs a map at least 3 by 3".to_string());
+        }
+
+        let mut rng = Pcg32::new(seed);
+        let mut map_data = solid(width, height, seed);
+
+        let inside = (width - 2) * (height - 2);
+        let wanted =
+            ((inside as f64 * self.coverage) as usize).clamp(1, inside);
+
+        // gives up eventually so an unlucky walk can not go on forever
+        let max_steps = inside * 100;
+
+        let directions = [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ];
+
+        let mut at = Coord::new(width as isize / 2, height as isize / 2);
+        let mut dug = 0;
+
+        for _ in 0..max_steps {
+            let pos = map_data.index(at).expect("the walker stays on the map");
+
+            if map_data.map[pos] == Tile::Wall {
+                map_data.map[pos] = Tile::Floor;
+                dug += 1;
+
+                if dug >= wanted {
+                    break;
+                }
+            }
+
+            let next = at.step(*rng.choose(&directions).unwrap());
+
+            // keep a wall around the edge
+            if next.x > 0
+                && next.y > 0
+                && next.x < width as isize - 1
+                && next.y < height as isize - 1
+            {
+                at = next;
+            }
+        }
+
+        Ok(map_data)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MazeKind {
+    /// long winding passages with few dead ends
+    Backtracker,
+    /// lots of short dead ends branching off
+    Prim,
+    /// an even mix of both
+    Kruskal,
+}
+
+/// a perfect maze, exactly one path between any two tiles
+///
+/// cells sit on odd coordinates with walls between them, so a maze is
+/// `(width - 1) / 2` cells across
+pub struct Maze {
+    pub kind: MazeKind,
+}
+
+impl Maze {
+    pub fn new(kind: MazeKind) -> Self {
+        Self { kind }
+    }
+}
+
+// the cells of a maze and the walls between them
+struct Cells {
+    width: usize,
+    height: usize,
+}
+
+impl Cells {
+    fn len(&self) -> usize {
+        self.width * self.height
+    }
+
+    // the cells next to `cell` that are on the maze
+    fn neighbors(&self, cell: usize) -> Vec<usize> {
+        let here = Coord::from_index(cell, self.width);
+
+        [
+            Direction::North,
+            Direction::East,
+            Direction::South,
+            Direction::West,
+        ]
+        .into_iter()
+        .filter_map(|dir| here.step(dir).to_index(self.width, self.height))
+        .collect()
+    }
+
+    // the tile of a cell on the map
+    fn tile(&self, cell: usize) -> Coord {
+        let cell = Coord::from_index(cell, self.width);
+
+        Coord::new(cell.x * 2 + 1, cell.y * 2 + 1)
+    }
+}
+
+impl Maze {
+    // open the two cells and the wall between them
+    fn carve(map_data: &mut MapData, cells: &Cells, a: usize, b: usize) {
+        let (a, b) = (cells.tile(a), cells.tile(b));
+        let between = Coord::new((a.x + b.x) / 2, (a.y + b.y) / 2);
+
+        for coord in [a, between, b] {
+            let pos = map_data.index(coord).expect("cells are on the map");
+
+            map_data.map[pos] = Tile::Floor;
+        }
+    }
+
+    fn backtracker(map_data: &mut MapData, cells: &Cells, rng: &mut Pcg32) {
+        let mut visited = vec![false; cells.len()];
+        let mut stack = vec![0];
+        visited[0] = true;
+
+        while let Some(&current) = stack.last() {
+            let unvisited: Vec<usize> = cells
+                .neighbors(current)
+                .into_iter()
+                .filter(|cell| !visited[*cell])
+                .collect();
+
+            match rng.choose(&unvisited) {
+                Some(&next) => {
+                    Self::carve(map_data, cells, current, next);
+                    visited[next] = true;
+                    stack.push(next);
+                }
+                None => {
+                    stack.pop();
+                }
+            }
+        }
+    }
+
+    fn prim(map_data: &mut MapData, cells: &Cells, rng: &mut Pcg32) {
+        let mut in_maze = vec![false; cells.len()];
+        in_maze[0] = true;
+
+        let mut walls: Vec<(usize, usize)> = cells
+            .neighbors(0)
+            .into_iter()
+            .map(|next| (0, next))
+            .collect();
+
+        while !walls.is_empty() {
+            let (from, to) = walls.swap_remove(rng.range(0..walls.len()));
+
+            if in_maze[to] {
+                continue;
+            }
+
+            Self::carve(map_data, cells, from, to);
+            in_maze[to] = true;
+
+            walls.extend(
+                cells
+                    .neighbors(to)
+                    .into_iter()
+                    .filter(|next| !in_maze[*next])
+                    .map(|next| (to, next)),
+            );
+        }
+    }
+
+    fn kruskal(map_data: &mut MapData, cells: &Cells, rng: &mut Pcg32) {
+        let mut sets: Vec<usize> = (0..cells.len()).collect();
+
+        fn find(sets: &mut [usize], cell: usize) -> usize {
+            let mut root = cell;
+
+            while sets[root] != root {
+                root = sets[root];
+            }
+
+            // point everything on the way straight at the root
+            let mut cell = cell;
+            while sets[cell] != root {
+                let next = sets[cell];
+                sets[cell] = root;
+                cell = next;
+            }
+
+            root
+        }
+
+        let mut walls: Vec<(usize, usize)> = (0..cells.len())
+            .flat_map(|cell| {
+                cells
+                    .neighbors(cell)
+                    .into_iter()
+                    .filter(move |next| *next > cell)
+                    .map(move |next| (cell, next))
+            })
+            .collect();
+
+        rng.shuffle(&mut walls);
+
+        for (a, b) in walls {
+            let (root_a, root_b) = (find(&mut sets, a), find(&mut sets, b));
+
+            if root_a != root_b {
+                sets[root_a] = root_b;
+                Self::carve(map_data, cells, a, b);
+            }
+        }
+    }
+}
+
+impl MapGenerator for Maze {
+    fn name(&self) -> &'static str {
+        match self.kind {
+            MazeKind::Backtracker => "backtracker",
+            MazeKind::Prim => "prim",
+            MazeKind::Kruskal => "kruskal",
+        }
+    }
+
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Result<MapData, String> {
+        // a cell and the wall on each side of it
+        let cells = match (width.checked_sub(1), height.checked_sub(1)) {
+            (Some(width), Some(height)) if width >= 2 && height >= 2 => Cells {
+                width: width / 2,
+                height: height / 2,
+            },
+            _ => return Err("mazes need a map at least 3 by 3".to_string()),
+        };
+
+        let mut rng = Pcg32::new(seed);
+        let mut map_data = solid(width, height, seed);
+
+        // a lone cell has nothing to join
+        let first = cells.tile(0);
+        let pos = map_data.index(first).expect("cells are on the map");
+        map_data.map[pos] = Tile::Floor;
+
+        match self.kind {
+            MazeKind::Backtracker => {
+                Self::backtracker(&mut map_data, &cells, &mut rng)
+            }
+            MazeKind::Prim => Self::prim(&mut map_data, &cells, &mut rng),
+            MazeKind::Kruskal => Self::kruskal(&mut map_data, &cells, &mut rng),
+        }
+
+        Ok(map_data)
+    }
+}
+
+/// open country from perlin noise, lakes in the low ground ringed by mud,
+/// grass in between and impassable hills on the high ground
+pub struct Terrain {
+    /// how many tiles across one hill or lake is, roughly
+    pub scale: f64,
+    /// layers of finer noise added on top
+    pub octaves: usize,
+}
+
+impl Default for Terrain {
+    fn default() -> Self {
+        Self {
+            scale: 16.0,
+            octaves: 4,
+        }
+    }
+}
+
+impl MapGenerator for Terrain {
+    fn name(&self) -> &'static str {
+        "terrain"
+    }
+
+    fn generate(
+        &self,
+        width: usize,
+        height: usize,
+        seed: u64,
+    ) -> Result<MapData, String> {
+        let noise = Perlin::new(seed);
+        let mut map_data = solid(width, height, seed);
+
+        for pos in 0..map_data.len() {
+            let coord = map_data.coord(pos);
+            let (mut x, mut y) =
+                (coord.x as f64 / self.scale, coord.y as f64 / self.scale);
+
+            let mut value = 0.0;
+            let mut amplitude = 1.0;
+            let mut total = 0.0;
+
+            for _ in 0..self.octaves.max(1) {
+                value += noise.get(x, y) * amplitude;
+                total += amplitude;
+
+                amplitude /= 2.0;
+                x *= 2.0;
+                y *= 2.0;
+            }
+
+            // perlin noise stays well inside -1 to 1, stretch it a bit
+            let height = (value / total * 1.5 + 1.0) / 2.0;
+
+            map_data.map[pos] = match height {
+                h if h < 0.3 => Tile::Water,
+                h if h < 0.36 => Tile::Mud,
+                h if h < 0.72 => Tile::Floor,
+                _ => Tile::Wall,
+            };
+        }
+
+        Ok(map_data)
+    }
+}
+
+/// classic 2d perlin noise, values fall between -1 and 1
+pub struct Perlin {
+    // a shuffled 0..256 twice over so lookups never need wrapping
+    permutation: Vec<usize>,
+}
+
+impl Perlin {
+    pub fn new(seed: u64) -> Self {
+        let mut rng = Pcg32::new(seed);
+        let mut permutation: Vec<usize> = (0..256).collect();
+
+        rng.shuffle(&mut permutation);
+        permutation.extend_from_within(..);
+
+        Self { permutation }
+    }
+
+    pub fn get(&self, x: f64, y: f64) -> f64 {
+        let (cell_x, cell_y) = (x.floor(), y.floor());
+        let (fx, fy) = (x - cell_x, y - cell_y);
+        let (cx, cy) =
+            (cell_x as i64 as usize & 255, cell_y as i64 as usize & 255);
+
+        let p = &self.permutation;
+        let corner = |dx: usize, dy: usize| p[p[cx + dx] + cy + dy];
+
+        let (u, v) = (fade(fx), fade(fy));
+
+        let top = lerp(
+            gradient(corner(0, 0), fx, fy),
+            gradient(corner(1, 0), fx - 1.0, fy),
+            u,
+        );
+        let bottom = lerp(
+            gradient(corner(0, 1), fx, fy - 1.0),
+            gradient(corner(1, 1), fx - 1.0, fy - 1.0),
+            u,
+        );
+
+        lerp(top, bottom, v)
+    }
+}
+
+fn fade(t: f64) -> f64 {
+    t * t * t * (t * (t * 6.0 - 15.0) + 10.0)
+}
+
+fn lerp(a: f64, b: f64, t: f64) -> f64 {
+    a + (b - a) * t
+}
+
+// the dot product of one of eight gradients, picked by `hash`, with the
+// offset from the corner
+fn gradient(hash: usize, x: f64, y: f64) -> f64 {
+    match hash & 7 {
+        0 => x + y,
+        1 => x - y,
+        2 => -x + y,
+        3 => -x - y,
+        4 => x,
+        5 => -x,
+        6 => y,
+        _ => -y,
+    }
+}
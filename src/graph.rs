@@ -0,0 +1,364 @@
+// search spaces
+//
+// what `AStar` and `Dijkstra` need from whatever they walk over, the nodes
+// next to a node with the cost of getting there and a guess at the distance
+// left. grids are one kind of space, navigation meshes, waypoint graphs and
+// quest or dialogue state graphs are others
+
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    hash::Hash,
+};
+
+use crate::{
+    astar::Heuristic,
+    clearance::Clearance,
+    coord::Coord,
+    links::AgentState,
+    map::{Grid, MapPos},
+    movement::{MovementProfile, StepCosts},
+};
+
+pub trait SearchSpace {
+    type Node: Copy + Eq + Hash + Ord;
+
+    /// every node reachable from `node` in one step with its cost
+    fn neighbors(&self, node: Self::Node) -> Vec<(Self::Node, isize)>;
+
+    /// the estimated cost from `from` to `to`, it must never overestimate
+    /// for a* to find the cheapest path, the default of zero is always safe
+    fn heuristic(&self, _from: Self::Node, _to: Self::Node) -> isize {
+        0
+    }
+}
+
+/// a grid searched tile by tile under some movement rules
+pub struct GridSpace<'a, G: Grid + ?Sized> {
+    grid: &'a G,
+    costs: StepCosts,
+    heuristic: Heuristic,
+    clearance: Option<(Clearance, usize)>,
+    agent: AgentState,
+    shortcuts: Option<Shortcuts>,
+}
+
+// where the grid's links start and end, a path that takes any of them walks
+// to one of the starts, pays at least the cheapest link and walks on from
+// one of the ends
+struct Shortcuts {
+    starts: Vec<Coord>,
+    ends: Vec<Coord>,
+    cheapest: isize,
+}
+
+impl<'a, G: Grid + ?Sized> GridSpace<'a, G> {
+    pub fn new(grid: &'a G) -> Self {
+        let links = grid.all_links();
+
+        let shortcuts =
+            links.iter().map(|link| link.cost).min().map(|cheapest| {
+                Shortcuts {
+                    starts: links
+                        .iter()
+                        .map(|link| grid.coord(link.from))
+                        .collect(),
+                    ends: links
+                        .iter()
+                        .map(|link| grid.coord(link.to))
+                        .collect(),
+                    cheapest,
+                }
+            });
+
+        Self {
+            grid,
+            costs: StepCosts::default(),
+            heuristic: Heuristic::default(),
+            clearance: None,
+            agent: AgentState::default(),
+            shortcuts,
+        }
+    }
+
+    pub fn with_heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    pub fn with_costs(mut self, costs: StepCosts) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    /// keep the step costs but move the way `profile` does
+    pub fn with_profile(mut self, profile: &MovementProfile) -> Self {
+        self.costs = self.costs.with_profile(profile);
+        self
+    }
+
+    /// only take steps an agent `size` tiles across fits through
+    pub fn with_clearance(mut self, clearance: Clearance, size: usize) -> Self {
+        self.clearance = Some((clearance, size));
+        self
+    }
+
+    /// take the links the agent is able to, without it only links that need
+    /// no key are taken
+    pub fn with_agent(mut self, agent: AgentState) -> Self {
+        self.agent = agent;
+        self
+    }
+
+    pub fn costs(&self) -> &StepCosts {
+        &self.costs
+    }
+}
+
+impl<G: Grid + ?Sized> SearchSpace for GridSpace<'_, G> {
+    type Node = MapPos;
+
+    fn neighbors(&self, pos: MapPos) -> Vec<(MapPos, isize)> {
+        let mut neighbors = self.costs.neighbors(self.grid, pos);
+        let mut links = self.costs.links(self.grid, pos, &self.agent);
+
+        if let Some((clearance, size)) = &self.clearance {
+            neighbors.retain(|(next, _)| clearance.allows(pos, *next, *size));
+            links.retain(|(next, _)| clearance.fits(*next, *size));
+        }
+
+        neighbors.extend(links);
+
+        neighbors
+    }
+
+    // the straight line guess, or the guess through the links when that is
+    // lower, since a link can jump further than the guess allows for
+    fn heuristic(&self, from: MapPos, to: MapPos) -> isize {
+        let from = self.grid.coord(from);
+        let to = self.grid.coord(to);
+        let distance = |a, b| self.heuristic.distance(a, b, &self.costs);
+
+        let direct = distance(from, to);
+
+        let Some(shortcuts) = &self.shortcuts else {
+            return direct;
+        };
+
+        // shortcuts are only made when there are links, so both are some
+        let to_link =
+            shortcuts.starts.iter().map(|start| distance(from, *start));
+        let from_link = shortcuts.ends.iter().map(|end| distance(*end, to));
+
+        let through = to_link.min().unwrap_or(0)
+            + shortcuts.cheapest * self.costs.straight
+            + from_link.min().unwrap_or(0);
+
+        direct.min(through)
+    }
+}
+
+/// a graph built out of explicit edges, like a waypoint graph
+#[derive(Clone, Debug)]
+pub struct Graph<N> {
+    edges: HashMap<N, Vec<(N, isize)>>,
+    heuristic: Option<fn(N, N) -> isize>,
+}
+
+impl<N: Copy + Eq + Hash + Ord> Default for Graph<N> {
+    fn default() -> Self {
+        Self {
+            edges: HashMap::new(),
+            heuristic: None,
+        }
+    }
+}
+
+impl<N: Copy + Eq + Hash + Ord> Graph<N> {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// estimate the distance between nodes with `heuristic` instead of zero
+    pub fn with_heuristic(mut self, heuristic: fn(N, N) -> isize) -> Self {
+        self.heuristic = Some(heuristic);
+        self
+    }
+
+    /// a one way edge from `from` to `to`
+    pub fn add_edge(&mut self, from: N, to: N, cost: isize) {
+        self.edges.entry(from).or_default().push((to, cost));
+    }
+
+    /// an edge that can be taken both ways at the same cost
+    pub fn connect(&mut self, a: N, b: N, cost: isize) {
+        self.add_edge(a, b, cost);
+        self.add_edge(b, a, cost);
+    }
+
+    pub fn nodes(&self) -> impl Iterator<Item = &N> {
+        self.edges.keys()
+    }
+}
+
+impl<N: Copy + Eq + Hash + Ord> SearchSpace for Graph<N> {
+    type Node = N;
+
+    fn neighbors(&self, node: N) -> Vec<(N, isize)> {
+        self.edges.get(&node).cloned().unwrap_or_default()
+    }
+
+    fn heuristic(&self, from: N, to: N) -> isize {
+        self.heuristic.map_or(0, |heuristic| heuristic(from, to))
+    }
+}
+
+/// breadth first over any search space, step costs are ignored so the path
+/// has the fewest steps rather than the lowest cost
+pub fn breadth_first<S: SearchSpace>(
+    space: &S,
+    start: S::Node,
+    end: S::Node,
+) -> HashMap<S::Node, S::Node> {
+    let mut frontier = VecDeque::from([start]);
+    let mut came_from = HashMap::new();
+    let mut reached = HashSet::from([start]);
+
+    while let Some(current) = frontier.pop_front() {
+        if current == end {
+            break;
+        }
+
+        for (next, _) in space.neighbors(current) {
+            if reached.insert(next) {
+                came_from.insert(next, current);
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    came_from
+}
+
+/// every node `start` can get to, `start` included
+pub fn reachable<S: SearchSpace>(
+    space: &S,
+    start: S::Node,
+) -> HashSet<S::Node> {
+    let mut frontier = VecDeque::from([start]);
+    let mut reached = HashSet::from([start]);
+
+    while let Some(current) = frontier.pop_front() {
+        for (next, _) in space.neighbors(current) {
+            if reached.insert(next) {
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    reached
+}
+
+/// every node out of the ones reachable from `from` that can reach `end`,
+/// found by walking the edges backwards so one way links are respected
+pub fn reaching<S: SearchSpace>(
+    space: &S,
+    from: &[S::Node],
+    end: S::Node,
+) -> HashSet<S::Node> {
+    let mut incoming: HashMap<S::Node, Vec<S::Node>> = HashMap::new();
+    let mut frontier: VecDeque<S::Node> = from.iter().copied().collect();
+    let mut seen: HashSet<S::Node> = from.iter().copied().collect();
+
+    while let Some(current) = frontier.pop_front() {
+        for (next, _) in space.neighbors(current) {
+            incoming.entry(next).or_default().push(current);
+
+            if seen.insert(next) {
+                frontier.push_back(next);
+            }
+        }
+    }
+
+    let mut frontier = VecDeque::from([end]);
+    let mut reached = HashSet::from([end]);
+
+    while let Some(current) = frontier.pop_front() {
+        for previous in incoming.get(&current).into_iter().flatten() {
+            if reached.insert(*previous) {
+                frontier.push_back(*previous);
+            }
+        }
+    }
+
+    reached
+}
+
+/// walk a came_from map back from the end, end first like the searches
+/// return their paths, empty when the end was never reached
+pub fn path_from<N: Copy + Eq + Hash>(
+    came_from: &HashMap<N, N>,
+    start: N,
+    end: N,
+) -> Vec<N> {
+    let mut path = vec![];
+    let mut current = end;
+
+    while current != start {
+        path.push(current);
+
+        match came_from.get(&current) {
+            Some(prev) => current = *prev,
+            None => return vec![],
+        }
+    }
+
+    path.push(start);
+
+    path
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        astar::AStar,
+        links::Link,
+        map::{MapData, Tile},
+    };
+
+    #[test]
+    fn heuristic_counts_links() {
+        let (width, height) = (40, 5);
+        let map = (0..width * height)
+            .map(|pos| {
+                let (x, y) = (pos % width, pos / width);
+
+                if x == 0 || y == 0 || x == width - 1 || y == height - 1 {
+                    Tile::Wall
+                } else {
+                    Tile::Floor
+                }
+            })
+            .collect();
+
+        let mut map_data = MapData::from_tiles(width, height, map);
+        let index = |x, y| x + y * width;
+
+        map_data.links.add(Link {
+            from: index(1, 2),
+            to: index(37, 2),
+            cost: 1,
+            key: None,
+        });
+
+        let space = GridSpace::new(&map_data)
+            .with_costs(StepCosts::octile())
+            .with_heuristic(Heuristic::Octile);
+
+        let mut astar = AStar::default();
+        let path = astar.run(&space, index(1, 2), index(38, 2));
+
+        assert_eq!(path.len(), 3);
+        assert_eq!(astar.cost(index(38, 2)), Some(2 * space.costs().straight));
+    }
+}
@@ -0,0 +1,599 @@
+// heightmaps
+//
+// outdoor levels as a grid of numbers, either the height of the ground or
+// what it costs to cross each cell. `SlopeSpace` and `WeightSpace` turn one
+// into something `AStar` and `Dijkstra` can search
+//
+// text heightmaps are one row per line with the values separated by
+// whitespace, `x` or `nan` marks a cell that can not be entered at all
+
+use std::{
+    fmt::Write as _,
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    coord::Direction,
+    graph::SearchSpace,
+    image::{Image, Rgb},
+    map::{Grid, MapPos, Tile},
+    rng::Pcg32,
+};
+
+/// the cost of a straight step, everything else is scaled from it so
+/// fractional slope costs survive as integers
+pub const STEP: isize = 100;
+
+/// the cost of a diagonal step, sqrt(2) times `STEP`
+pub const DIAGONAL_STEP: isize = 141;
+
+// darkest to brightest
+const RAMP: &[char] = &[' ', '.', ',', ':', ';', '=', '%', '#', '@'];
+
+#[derive(Clone, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HeightMap {
+    pub width: usize,
+    pub height: usize,
+    pub values: Vec<f64>,
+}
+
+impl HeightMap {
+    pub fn new(width: usize, height: usize, values: Vec<f64>) -> Self {
+        assert_eq!(values.len(), width * height, "heightmap size mismatch");
+
+        Self {
+            width,
+            height,
+            values,
+        }
+    }
+
+    pub fn from_ints(width: usize, height: usize, values: &[i64]) -> Self {
+        Self::new(width, height, values.iter().map(|v| *v as f64).collect())
+    }
+
+    /// rolling hills between 0 and 10, two layers of smoothed noise
+    pub fn generate(width: usize, height: usize, seed: u64) -> Self {
+        let mut rng = Pcg32::new(seed);
+        let mut values = vec![0.0; width * height];
+
+        for (cell, weight) in [(16, 0.75), (4, 0.25)] {
+            let grid_width = width / cell + 2;
+            let grid_height = height / cell + 2;
+            let lattice: Vec<f64> = (0..grid_width * grid_height)
+                .map(|_| rng.float() * 10.0)
+                .collect();
+
+            for y in 0..height {
+                for x in 0..width {
+                    let (gx, fx) = (x / cell, (x % cell) as f64 / cell as f64);
+                    let (gy, fy) = (y / cell, (y % cell) as f64 / cell as f64);
+                    let at = |x: usize, y: usize| lattice[x + y * grid_width];
+
+                    // smoothstep so the lattice does not show
+                    let (sx, sy) = (smooth(fx), smooth(fy));
+                    let top = at(gx, gy) + (at(gx + 1, gy) - at(gx, gy)) * sx;
+                    let bottom = at(gx, gy + 1)
+                        + (at(gx + 1, gy + 1) - at(gx, gy + 1)) * sx;
+
+                    values[x + y * width] +=
+                        weight * (top + (bottom - top) * sy);
+                }
+            }
+        }
+
+        Self::new(width, height, values)
+    }
+
+    pub fn get(&self, pos: MapPos) -> f64 {
+        self.values[pos]
+    }
+
+    /// whether the cell can be entered at all
+    pub fn is_open(&self, pos: MapPos) -> bool {
+        self.values[pos].is_finite()
+    }
+
+    /// the lowest and highest values, ignoring blocked cells
+    pub fn range(&self) -> (f64, f64) {
+        self.values
+            .iter()
+            .filter(|value| value.is_finite())
+            .fold((f64::MAX, f64::MIN), |(low, high), value| {
+                (low.min(*value), high.max(*value))
+            })
+    }
+
+    // how bright a cell is between 0 and 1 given the range, blocked cells
+    // are -1
+    fn shade(&self, pos: MapPos, (low, high): (f64, f64)) -> f64 {
+        if !self.is_open(pos) {
+            return -1.0;
+        }
+
+        if high > low {
+            (self.values[pos] - low) / (high - low)
+        } else {
+            0.0
+        }
+    }
+
+    /// the heightmap as ascii shades with the path on top, end first like the
+    /// searches return it
+    pub fn render(&self, path: &[MapPos], color: bool) -> String {
+        let mut out = String::with_capacity(self.values.len() * 2);
+        let range = self.range();
+
+        for pos in 0..self.values.len() {
+            let glyph = match (path.last(), path.first()) {
+                (Some(start), _) if *start == pos => Some('S'),
+                (_, Some(end)) if *end == pos => Some('E'),
+                _ if path.contains(&pos) => Some('+'),
+                _ => None,
+            };
+
+            match glyph {
+                Some(glyph) if color => {
+                    let _ = write!(out, "\x1b[38;5;40m{}\x1b[0m", glyph);
+                }
+                Some(glyph) => out.push(glyph),
+                None if !self.is_open(pos) => out.push('X'),
+                None => {
+                    let shade =
+                        self.shade(pos, range) * (RAMP.len() - 1) as f64;
+
+                    out.push(RAMP[shade.round() as usize]);
+                }
+            }
+
+            if (pos + 1) % self.width == 0 {
+                out.push('\n');
+            }
+        }
+
+        out
+    }
+
+    /// a grey picture of the heightmap, blocked cells are red
+    pub fn image(&self) -> Image {
+        let mut image = Image::new(self.width, self.height, [0, 0, 0]);
+        let range = self.range();
+
+        for pos in 0..self.values.len() {
+            let shade = self.shade(pos, range);
+
+            image.pixels[pos] = if shade < 0.0 {
+                [160, 30, 30]
+            } else {
+                let grey = (40.0 + shade * 200.0) as u8;
+                [grey, grey, grey] as Rgb
+            };
+        }
+
+        image
+    }
+}
+
+fn smooth(t: f64) -> f64 {
+    t * t * (3.0 - 2.0 * t)
+}
+
+/// blocked cells are walls and everything else floor, so the grid helpers
+/// work on heightmaps too
+impl Grid for HeightMap {
+    fn width(&self) -> usize {
+        self.width
+    }
+
+    fn height(&self) -> usize {
+        self.height
+    }
+
+    fn tile(&self, pos: MapPos) -> Tile {
+        if self.is_open(pos) {
+            Tile::Floor
+        } else {
+            Tile::Wall
+        }
+    }
+}
+
+// every open neighbor with the base cost of the step there
+fn steps(
+    map: &HeightMap,
+    pos: MapPos,
+) -> impl Iterator<Item = (MapPos, isize)> + '_ {
+    Direction::ALL.into_iter().filter_map(move |dir| {
+        let next = map.index(map.coord(pos).step(dir))?;
+
+        if !map.is_open(next) {
+            return None;
+        }
+
+        Some((
+            next,
+            if dir.is_diagonal() {
+                DIAGONAL_STEP
+            } else {
+                STEP
+            },
+        ))
+    })
+}
+
+// octile distance in `STEP` units
+fn octile(map: &HeightMap, a: MapPos, b: MapPos) -> isize {
+    let delta = (map.coord(a) - map.coord(b)).abs();
+
+    STEP * delta.x.max(delta.y) + (DIAGONAL_STEP - STEP) * delta.x.min(delta.y)
+}
+
+/// how the ground's shape changes what a step costs
+#[derive(Clone, Copy, PartialEq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SlopeCosts {
+    /// extra cost per unit of height climbed, in straight steps
+    pub climb: f64,
+    /// extra cost per unit of height dropped, in straight steps
+    pub descent: f64,
+    /// the steepest slope a step can climb, rise over distance, `None` for
+    /// no limit
+    pub max_climb: Option<f64>,
+}
+
+impl Default for SlopeCosts {
+    fn default() -> Self {
+        Self {
+            climb: 1.0,
+            descent: 0.0,
+            max_climb: None,
+        }
+    }
+}
+
+impl SlopeCosts {
+    /// slopes only ever add to a step's cost, a negative climb or descent
+    /// would make steps cost less than nothing
+    pub fn validate(&self) -> Result<(), String> {
+        for (name, cost) in [("climb", self.climb), ("descent", self.descent)] {
+            if cost.is_nan() || cost < 0.0 {
+                return Err(format!(
+                    "{} must be at least 0, got {}",
+                    name, cost
+                ));
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// a heightmap searched by elevation, climbing costs more than walking on
+/// the flat and slopes steeper than `max_climb` can not be climbed
+pub struct SlopeSpace<'a> {
+    map: &'a HeightMap,
+    costs: SlopeCosts,
+}
+
+impl<'a> SlopeSpace<'a> {
+    /// fails when the costs would make a step cheaper than walking on the
+    /// flat
+    pub fn new(map: &'a HeightMap, costs: SlopeCosts) -> Result<Self, String> {
+        costs.validate()?;
+
+        Ok(Self { map, costs })
+    }
+}
+
+impl SearchSpace for SlopeSpace<'_> {
+    type Node = MapPos;
+
+    fn neighbors(&self, pos: MapPos) -> Vec<(MapPos, isize)> {
+        let here = self.map.get(pos);
+
+        steps(self.map, pos)
+            .filter_map(|(next, base)| {
+                let rise = self.map.get(next) - here;
+                let distance = base as f64 / STEP as f64;
+
+                if self
+                    .costs
+                    .max_climb
+                    .is_some_and(|max| rise / distance > max)
+                {
+                    return None;
+                }
+
+                let extra = if rise > 0.0 {
+                    self.costs.climb * rise
+                } else {
+                    self.costs.descent * -rise
+                };
+
+                Some((next, base + (extra * STEP as f64).round() as isize))
+            })
+            .collect()
+    }
+
+    // the climbs only ever add to the flat cost
+    fn heuristic(&self, from: MapPos, to: MapPos) -> isize {
+        octile(self.map, from, to)
+    }
+}
+
+/// a heightmap searched by weight, each value is what entering the cell
+/// costs in steps and values below zero can not be entered
+///
+/// a weight of zero is a cell that costs nothing to enter, the heuristic
+/// drops to zero along with it
+pub struct WeightSpace<'a> {
+    map: &'a HeightMap,
+    // a straight and a diagonal step onto the lightest cell, rounded the way
+    // the steps are so the heuristic never guesses above a real path
+    straight: isize,
+    diagonal: isize,
+}
+
+impl<'a> WeightSpace<'a> {
+    pub fn new(map: &'a HeightMap) -> Self {
+        let lightest = map
+            .values
+            .iter()
+            .copied()
+            .filter(|value| *value >= 0.0 && value.is_finite())
+            .reduce(f64::min)
+            .unwrap_or(0.0);
+
+        let straight = step_cost(STEP, lightest);
+
+        Self {
+            map,
+            straight,
+            // two straight steps get there too when rounding makes them
+            // cheaper
+            diagonal: step_cost(DIAGONAL_STEP, lightest).min(2 * straight),
+        }
+    }
+}
+
+// a step of `base` onto a cell of `weight`
+fn step_cost(base: isize, weight: f64) -> isize {
+    (base as f64 * weight).round() as isize
+}
+
+impl SearchSpace for WeightSpace<'_> {
+    type Node = MapPos;
+
+    fn neighbors(&self, pos: MapPos) -> Vec<(MapPos, isize)> {
+        steps(self.map, pos)
+            .filter(|(next, _)| self.map.get(*next) >= 0.0)
+            .map(|(next, base)| (next, step_cost(base, self.map.get(next))))
+            .collect()
+    }
+
+    fn heuristic(&self, from: MapPos, to: MapPos) -> isize {
+        let delta = (self.map.coord(from) - self.map.coord(to)).abs();
+        let diagonals = delta.x.min(delta.y);
+
+        self.straight * (delta.x.max(delta.y) - diagonals)
+            + self.diagonal * diagonals
+    }
+}
+
+pub fn read_heights<R: Read>(reader: R) -> io::Result<HeightMap> {
+    let mut rows: Vec<Vec<f64>> = vec![];
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty() {
+            continue;
+        }
+
+        let row = line
+            .split_whitespace()
+            .map(|value| match value {
+                "x" | "X" | "nan" => Ok(f64::NAN),
+                _ => value.parse().map_err(|_| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidData,
+                        format!("line {}: bad value `{}`", i + 1, value),
+                    )
+                }),
+            })
+            .collect::<io::Result<Vec<f64>>>()?;
+
+        if rows.first().is_some_and(|first| first.len() != row.len()) {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                format!("line {}: rows need the same number of values", i + 1),
+            ));
+        }
+
+        rows.push(row);
+    }
+
+    let width = rows.first().map_or(0, |row| row.len());
+
+    if width == 0 {
+        return Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "the heightmap is empty",
+        ));
+    }
+
+    let height = rows.len();
+
+    Ok(HeightMap::new(width, height, rows.concat()))
+}
+
+pub fn write_heights<W: Write>(
+    writer: &mut W,
+    map: &HeightMap,
+) -> io::Result<()> {
+    for row in map.values.chunks(map.width) {
+        let line: Vec<String> = row
+            .iter()
+            .map(|value| {
+                if value.is_finite() {
+                    format!("{:.2}", value)
+                } else {
+                    "x".to_string()
+                }
+            })
+            .collect();
+
+        writeln!(writer, "{}", line.join(" "))?;
+    }
+
+    Ok(())
+}
+
+pub fn load_heights<P: AsRef<Path>>(path: P) -> io::Result<HeightMap> {
+    read_heights(fs::File::open(path)?)
+}
+
+pub fn save_heights<P: AsRef<Path>>(
+    path: P,
+    map: &HeightMap,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(fs::File::create(path)?);
+
+    write_heights(&mut writer, map)?;
+
+    writer.flush()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{astar::AStar, dijkstra::Dijkstra};
+
+    fn cost_to(
+        space: &impl SearchSpace<Node = MapPos>,
+        from: MapPos,
+        to: MapPos,
+    ) -> isize {
+        space
+            .neighbors(from)
+            .into_iter()
+            .find(|(next, _)| *next == to)
+            .map(|(_, cost)| cost)
+            .expect("the step is allowed")
+    }
+
+    #[test]
+    fn climbs_and_descents_add_to_steps() {
+        // a flat cell, a step up by 2 and a diagonal step back down by 1
+        let map = HeightMap::from_ints(2, 2, &[0, 2, 0, 1]);
+        let costs = SlopeCosts {
+            climb: 1.5,
+            descent: 0.25,
+            max_climb: None,
+        };
+        let space = SlopeSpace::new(&map, costs).unwrap();
+
+        assert_eq!(cost_to(&space, 0, 2), STEP);
+        assert_eq!(cost_to(&space, 0, 1), STEP + 300);
+        assert_eq!(cost_to(&space, 1, 2), DIAGONAL_STEP + 50);
+    }
+
+    #[test]
+    fn slopes_past_the_limit_are_refused() {
+        let map = HeightMap::from_ints(3, 1, &[0, 2, 0]);
+        let costs = SlopeCosts {
+            max_climb: Some(1.0),
+            ..SlopeCosts::default()
+        };
+        let space = SlopeSpace::new(&map, costs).unwrap();
+
+        // too steep to climb but fine to walk down
+        assert!(space.neighbors(0).iter().all(|(next, _)| *next != 1));
+        assert!(space.neighbors(1).iter().any(|(next, _)| *next == 0));
+
+        let mut astar = AStar::default();
+        assert!(astar.run(&space, 0, 2).is_empty());
+    }
+
+    #[test]
+    fn negative_slope_costs_are_refused() {
+        let map = HeightMap::from_ints(2, 1, &[0, 1]);
+        let costs = SlopeCosts {
+            descent: -1.0,
+            ..SlopeCosts::default()
+        };
+
+        assert!(SlopeSpace::new(&map, costs).is_err());
+    }
+
+    #[test]
+    fn weight_heuristic_never_overestimates() {
+        // each diagonal rounds 42.3 down to 42, so 4 of them cost 168
+        let map = HeightMap::new(5, 5, vec![0.3; 25]);
+        let space = WeightSpace::new(&map);
+
+        let mut dijkstra = Dijkstra::default();
+        dijkstra.run(&space, 0, 24);
+
+        assert_eq!(dijkstra.cost(24), Some(168));
+        assert!(space.heuristic(0, 24) <= 168);
+    }
+
+    #[test]
+    fn zero_weights_cost_nothing() {
+        let map = HeightMap::new(4, 1, vec![1.0, 0.0, 0.0, 1.0]);
+        let space = WeightSpace::new(&map);
+
+        assert_eq!(space.heuristic(0, 3), 0);
+
+        let mut astar = AStar::default();
+        astar.run(&space, 0, 3);
+
+        assert_eq!(astar.cost(3), Some(STEP));
+    }
+
+    #[test]
+    fn astar_matches_dijkstra() {
+        let map = HeightMap::generate(40, 30, 9);
+        let weights = HeightMap::new(
+            40,
+            30,
+            map.values.iter().map(|value| value / 7.0).collect(),
+        );
+        let (start, end) = (0, map.values.len() - 1);
+
+        let slopes = SlopeSpace::new(
+            &map,
+            SlopeCosts {
+                climb: 2.0,
+                descent: 0.5,
+                max_climb: Some(3.0),
+            },
+        )
+        .unwrap();
+
+        let mut astar = AStar::default();
+        let mut dijkstra = Dijkstra::default();
+
+        astar.run(&slopes, start, end);
+        dijkstra.run(&slopes, start, end);
+        assert!(astar.cost(end).is_some());
+        assert_eq!(
+            astar.cost(end),
+            dijkstra.cost(end).map(|cost| cost as isize)
+        );
+
+        let weighted = WeightSpace::new(&weights);
+
+        astar.run(&weighted, start, end);
+        dijkstra.run(&weighted, start, end);
+        assert!(astar.cost(end).is_some());
+        assert_eq!(
+            astar.cost(end),
+            dijkstra.cost(end).map(|cost| cost as isize)
+        );
+    }
+}
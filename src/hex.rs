@@ -0,0 +1,365 @@
+// hex grids
+//
+// pointy topped hexes, addressed with axial coordinates and stored in odd-r
+// offset rows so a hex map is still a flat vec of tiles. odd rows sit half a
+// hex to the right of even rows
+//
+// see https://www.redblobgames.com/grids/hexagons/ for the coordinate math
+
+use std::fmt::Write as _;
+
+use crate::{
+    graph::SearchSpace,
+    image::{self, tile_color, Image},
+    map::{MapPos, Tile},
+    movement::MovementProfile,
+    rng::Pcg32,
+};
+
+/// the six directions in axial coordinates, starting east and going
+/// counter clockwise
+pub const DIRECTIONS: [Axial; 6] = [
+    Axial { q: 1, r: 0 },
+    Axial { q: 1, r: -1 },
+    Axial { q: 0, r: -1 },
+    Axial { q: -1, r: 0 },
+    Axial { q: -1, r: 1 },
+    Axial { q: 0, r: 1 },
+];
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Axial {
+    pub q: isize,
+    pub r: isize,
+}
+
+impl Axial {
+    pub fn new(q: isize, r: isize) -> Self {
+        Self { q, r }
+    }
+
+    /// the cube coordinates, they always add up to zero
+    pub fn cube(self) -> (isize, isize, isize) {
+        (self.q, self.r, -self.q - self.r)
+    }
+
+    /// the hex at an odd-r offset column and row, the inverse of
+    /// `to_offset` for rows on either side of zero
+    pub fn from_offset(col: isize, row: isize) -> Self {
+        Self::new(col - (row - (row & 1)) / 2, row)
+    }
+
+    pub fn to_offset(self) -> (isize, isize) {
+        (self.q + (self.r - (self.r & 1)) / 2, self.r)
+    }
+
+    pub fn add(self, other: Axial) -> Self {
+        Self::new(self.q + other.q, self.r + other.r)
+    }
+
+    /// the number of steps between two hexes
+    pub fn distance(self, other: Axial) -> isize {
+        let (x, y, z) = self.cube();
+        let (ox, oy, oz) = other.cube();
+
+        (x - ox).abs().max((y - oy).abs()).max((z - oz).abs())
+    }
+
+    pub fn neighbors(self) -> impl Iterator<Item = Axial> {
+        DIRECTIONS.into_iter().map(move |dir| self.add(dir))
+    }
+
+    /// every hex on the straight line from `self` to `other`, both ends
+    /// included
+    pub fn line(self, other: Axial) -> Vec<Axial> {
+        let steps = self.distance(other);
+        let (x, y, z) = self.cube();
+        let (ox, oy, oz) = other.cube();
+
+        (0..=steps)
+            .map(|i| {
+                let t = if steps == 0 {
+                    0.0
+                } else {
+                    i as f64 / steps as f64
+                };
+                // nudged so points on an edge round the same way every time
+                let lerp =
+                    |a: isize, b: isize| a as f64 + 1e-6 + (b - a) as f64 * t;
+
+                cube_round(lerp(x, ox), lerp(y, oy), lerp(z, oz))
+            })
+            .collect()
+    }
+}
+
+fn cube_round(x: f64, y: f64, z: f64) -> Axial {
+    let (mut rx, mut ry, rz) = (x.round(), y.round(), z.round());
+
+    let (dx, dy, dz) = ((rx - x).abs(), (ry - y).abs(), (rz - z).abs());
+
+    // fix whichever moved the most so they still add up to zero
+    if dx > dy && dx > dz {
+        rx = -ry - rz;
+    } else if dy > dz {
+        ry = -rx - rz;
+    }
+
+    Axial::new(rx as isize, ry as isize)
+}
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct HexMap {
+    pub seed: u64,
+    pub width: usize,
+    pub height: usize,
+    pub tiles: Vec<Tile>,
+}
+
+impl HexMap {
+    /// an all wall map
+    pub fn new(width: usize, height: usize, seed: u64) -> Self {
+        Self {
+            seed,
+            width,
+            height,
+            tiles: vec![Tile::Wall; width * height],
+        }
+    }
+
+    pub fn index(&self, hex: Axial) -> Option<MapPos> {
+        let (col, row) = hex.to_offset();
+
+        if col < 0
+            || row < 0
+            || col >= self.width as isize
+            || row >= self.height as isize
+        {
+            return None;
+        }
+
+        Some(col as usize + row as usize * self.width)
+    }
+
+    pub fn hex(&self, pos: MapPos) -> Axial {
+        Axial::from_offset(
+            (pos % self.width) as isize,
+            (pos / self.width) as isize,
+        )
+    }
+
+    /// round rooms joined by straight corridors, the hex take on
+    /// `MapData::gen`
+    pub fn gen(&mut self) {
+        let mut rng = Pcg32::new(self.seed);
+        let mut centers: Vec<Axial> = vec![];
+
+        for _ in 0..30 {
+            let radius = rng.range(1..=3);
+
+            if self.width <= radius * 2 + 2 || self.height <= radius * 2 + 2 {
+                break;
+            }
+
+            let col = rng.range(radius + 1..self.width - radius - 1);
+            let row = rng.range(radius + 1..self.height - radius - 1);
+            let center = Axial::from_offset(col as isize, row as isize);
+
+            if centers
+                .iter()
+                .any(|other| other.distance(center) <= radius as isize + 4)
+            {
+                continue;
+            }
+
+            self.carve_room(center, radius as isize);
+
+            if let Some(last) = centers.last() {
+                for hex in last.line(center) {
+                    self.carve(hex);
+                }
+            }
+
+            centers.push(center);
+        }
+    }
+
+    fn carve(&mut self, hex: Axial) {
+        if let Some(pos) = self.index(hex) {
+            self.tiles[pos] = Tile::Floor;
+        }
+    }
+
+    fn carve_room(&mut self, center: Axial, radius: isize) {
+        for q in -radius..=radius {
+            for r in (-radius).max(-q - radius)..=radius.min(-q + radius) {
+                self.carve(center.add(Axial::new(q, r)));
+            }
+        }
+    }
+
+    /// the map as ascii, every hex is two characters wide and odd rows are
+    /// pushed over by one, the path is end first like the searches return it
+    pub fn render(&self, path: &[MapPos], color: bool) -> String {
+        let mut out = String::new();
+
+        for row in 0..self.height {
+            if row % 2 == 1 {
+                out.push(' ');
+            }
+
+            for col in 0..self.width {
+                let pos = col + row * self.width;
+
+                let glyph = match (path.last(), path.first()) {
+                    (Some(start), _) if *start == pos => 'S',
+                    (_, Some(end)) if *end == pos => 'E',
+                    _ if path.contains(&pos) => '+',
+                    _ => self.tiles[pos].glyph(),
+                };
+
+                if color && path.contains(&pos) {
+                    let _ = write!(out, "\x1b[38;5;40m{}\x1b[0m ", glyph);
+                } else {
+                    out.push(glyph);
+                    out.push(' ');
+                }
+            }
+
+            out.push('\n');
+        }
+
+        out
+    }
+
+    /// a picture of the map, each hex is two pixels wide with odd rows
+    /// shifted by one so neighbors touch like they do on the board
+    pub fn image(&self, path: &[MapPos]) -> Image {
+        let mut image = Image::new(self.width * 2 + 1, self.height, [0, 0, 0]);
+
+        let mut fill = |pos: MapPos, color| {
+            let (col, row) = (pos % self.width, pos / self.width);
+            let x = col * 2 + row % 2;
+
+            image.set(x, row, color);
+            image.set(x + 1, row, color);
+        };
+
+        for (pos, tile) in self.tiles.iter().enumerate() {
+            fill(pos, tile_color(*tile));
+        }
+
+        for pos in path {
+            fill(*pos, image::PATH_COLORS[0]);
+        }
+
+        if let (Some(start), Some(end)) = (path.last(), path.first()) {
+            fill(*start, image::START);
+            fill(*end, image::END);
+        }
+
+        image
+    }
+}
+
+/// a hex map searched hex by hex, every step costs the same before the
+/// terrain is taken into account
+pub struct HexSpace<'a> {
+    map: &'a HexMap,
+    profile: MovementProfile,
+}
+
+impl<'a> HexSpace<'a> {
+    pub fn new(map: &'a HexMap) -> Self {
+        Self {
+            map,
+            profile: MovementProfile::default(),
+        }
+    }
+
+    pub fn with_profile(mut self, profile: &MovementProfile) -> Self {
+        self.profile = *profile;
+        self
+    }
+}
+
+impl SearchSpace for HexSpace<'_> {
+    type Node = MapPos;
+
+    fn neighbors(&self, pos: MapPos) -> Vec<(MapPos, isize)> {
+        self.map
+            .hex(pos)
+            .neighbors()
+            .filter_map(|hex| {
+                let next = self.map.index(hex)?;
+                let cost = self.profile.terrain.get(self.map.tiles[next])?;
+
+                Some((next, cost))
+            })
+            .collect()
+    }
+
+    // no terrain is cheaper than one per step
+    fn heuristic(&self, from: MapPos, to: MapPos) -> isize {
+        self.map.hex(from).distance(self.map.hex(to))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::astar::AStar;
+
+    #[test]
+    fn offset_round_trip() {
+        for q in -6..=6 {
+            for r in -6..=6 {
+                let hex = Axial::new(q, r);
+                let (col, row) = hex.to_offset();
+
+                assert_eq!(Axial::from_offset(col, row), hex);
+            }
+        }
+
+        // odd rows sit half a hex to the right, negative ones too
+        assert_eq!(Axial::new(0, -1).to_offset(), (-1, -1));
+        assert_eq!(Axial::new(0, 1).to_offset(), (0, 1));
+        assert_eq!(Axial::from_offset(0, -1), Axial::new(1, -1));
+    }
+
+    #[test]
+    fn neighbors_are_one_step_away() {
+        let hex = Axial::new(2, -3);
+
+        for next in hex.neighbors() {
+            assert_eq!(hex.distance(next), 1);
+        }
+
+        assert_eq!(hex.distance(Axial::new(-1, 0)), 3);
+    }
+
+    #[test]
+    fn shortest_path_goes_around_a_wall() {
+        let mut map = HexMap::new(5, 5, 0);
+        map.tiles.fill(Tile::Floor);
+
+        let pos = |col: usize, row: usize| col + row * map.width;
+        let (start, end) = (pos(0, 2), pos(4, 2));
+
+        let mut astar = AStar::default();
+        astar.run(&HexSpace::new(&map), start, end);
+
+        assert_eq!(astar.cost(end), Some(4));
+
+        // one hex in the way costs one more step to go round
+        map.tiles[pos(2, 2)] = Tile::Wall;
+
+        let path = astar.run(&HexSpace::new(&map), start, end);
+
+        assert_eq!(astar.cost(end), Some(5));
+        assert_eq!(path.len(), 6);
+        assert!(!path.contains(&pos(2, 2)));
+    }
+}
@@ -0,0 +1,612 @@
+// image export
+//
+// plain ppm and png writers so big maps can be looked at outside of the
+// terminal, the png is written with stored (uncompressed) deflate blocks so
+// there is nothing to pull in for it
+
+use std::{
+    collections::{HashMap, HashSet},
+    fs::File,
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    map::{MapData, MapPos, Tile},
+    render::SearchTrace,
+};
+
+pub type Rgb = [u8; 3];
+
+pub const WALL: Rgb = [40, 40, 40];
+pub const FLOOR: Rgb = [220, 220, 220];
+pub const WATER: Rgb = [70, 130, 200];
+pub const MUD: Rgb = [130, 100, 60];
+pub const ROAD: Rgb = [170, 170, 170];
+pub const DOOR: Rgb = [160, 90, 30];
+pub const LAVA: Rgb = [230, 90, 20];
+pub const STAIRS: Rgb = [250, 230, 120];
+pub const CLOSED: Rgb = [120, 150, 220];
+pub const OPEN: Rgb = [240, 200, 60];
+pub const START: Rgb = [230, 30, 30];
+pub const END: Rgb = [200, 30, 200];
+
+/// colors handed out to paths in order, so a few can share an image
+pub const PATH_COLORS: &[Rgb] = &[
+    [40, 180, 40],
+    [30, 110, 230],
+    [240, 120, 20],
+    [150, 60, 200],
+    [20, 190, 190],
+];
+
+// the biggest payload a stored deflate block can hold
+const MAX_STORED_BLOCK: usize = 65_535;
+
+// gif codes top out at 12 bits
+const MAX_GIF_CODES: u16 = 4096;
+
+#[derive(Clone)]
+pub struct Image {
+    pub width: usize,
+    pub height: usize,
+    pub pixels: Vec<Rgb>,
+}
+
+impl Image {
+    pub fn new(width: usize, height: usize, fill: Rgb) -> Self {
+        Self {
+            width,
+            height,
+            pixels: vec![fill; width * height],
+        }
+    }
+
+    pub fn set(&mut self, x: usize, y: usize, color: Rgb) {
+        if x < self.width && y < self.height {
+            self.pixels[x + y * self.width] = color;
+        }
+    }
+
+    /// scale every pixel up into a `scale` by `scale` block
+    pub fn scaled(&self, scale: usize) -> Image {
+        let scale = scale.max(1);
+
+        let mut out =
+            Image::new(self.width * scale, self.height * scale, [0, 0, 0]);
+
+        for y in 0..out.height {
+            for x in 0..out.width {
+                out.pixels[x + y * out.width] =
+                    self.pixels[x / scale + (y / scale) * self.width];
+            }
+        }
+
+        out
+    }
+
+    pub fn write_ppm<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        write!(writer, "P6\n{} {}\n255\n", self.width, self.height)?;
+
+        for pixel in &self.pixels {
+            writer.write_all(pixel)?;
+        }
+
+        Ok(())
+    }
+
+    pub fn write_png<W: Write>(&self, writer: &mut W) -> io::Result<()> {
+        writer
+            .write_all(&[0x89, b'P', b'N', b'G', b'\r', b'\n', 0x1a, b'\n'])?;
+
+        let mut header = Vec::with_capacity(13);
+        header.extend_from_slice(&(self.width as u32).to_be_bytes());
+        header.extend_from_slice(&(self.height as u32).to_be_bytes());
+        // 8 bit depth, truecolor, default compression, filter and interlace
+        header.extend_from_slice(&[8, 2, 0, 0, 0]);
+
+        write_chunk(writer, b"IHDR", &header)?;
+
+        // every scanline starts with its filter type, 0 is none
+        let mut raw = Vec::with_capacity(self.height * (self.width * 3 + 1));
+        for row in self.pixels.chunks(self.width.max(1)) {
+            raw.push(0);
+
+            for pixel in row {
+                raw.extend_from_slice(pixel);
+            }
+        }
+
+        write_chunk(writer, b"IDAT", &zlib_stored(&raw))?;
+        write_chunk(writer, b"IEND", &[])
+    }
+
+    /// write the image out picking the format from the file extension,
+    /// anything that is not `.png` is written as ppm
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> io::Result<()> {
+        let path = path.as_ref();
+
+        let mut writer = BufWriter::new(File::create(path)?);
+
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("png") => self.write_png(&mut writer)?,
+            _ => self.write_ppm(&mut writer)?,
+        }
+
+        writer.flush()
+    }
+}
+
+/// a one pixel per tile picture of the map
+pub fn map_image(map_data: &MapData) -> Image {
+    let mut image = Image::new(map_data.map_width, map_data.map_height, FLOOR);
+
+    for (i, tile) in map_data.map.iter().enumerate() {
+        image.pixels[i] = tile_color(*tile);
+    }
+
+    image
+}
+
+pub fn tile_color(tile: Tile) -> Rgb {
+    match tile {
+        Tile::Wall => WALL,
+        Tile::Floor => FLOOR,
+        Tile::Water => WATER,
+        Tile::Mud => MUD,
+        Tile::Road => ROAD,
+        Tile::Door => DOOR,
+        Tile::Lava => LAVA,
+        Tile::StairsUp | Tile::StairsDown | Tile::Ladder => STAIRS,
+    }
+}
+
+/// color the closed set and the frontier of a search
+pub fn draw_trace(image: &mut Image, trace: &SearchTrace) {
+    for pos in &trace.closed {
+        image.pixels[*pos] = CLOSED;
+    }
+
+    for pos in &trace.open {
+        image.pixels[*pos] = OPEN;
+    }
+}
+
+/// color every tile the search put a cost on, walls are left alone so the
+/// layout stays readable
+pub fn draw_heat(image: &mut Image, map_data: &MapData, trace: &SearchTrace) {
+    let max_cost = trace.max_cost();
+
+    for (pos, cost) in &trace.cost {
+        if map_data.map[*pos] == Tile::Wall {
+            continue;
+        }
+
+        image.pixels[*pos] = heat_color(*cost, max_cost);
+    }
+}
+
+/// draw a path end first like the searches return it, the start and end get
+/// their own colors
+pub fn draw_path(image: &mut Image, path: &[MapPos], color: Rgb) {
+    for step in path {
+        image.pixels[*step] = color;
+    }
+
+    if let (Some(start), Some(end)) = (path.last(), path.first()) {
+        image.pixels[*start] = START;
+        image.pixels[*end] = END;
+    }
+}
+
+/// draw a few paths at once, each in the next color from `PATH_COLORS`
+pub fn draw_paths(image: &mut Image, paths: &[Vec<MapPos>]) {
+    for (path, color) in paths.iter().zip(PATH_COLORS.iter().cycle()) {
+        draw_path(image, path, *color);
+    }
+}
+
+/// blue through green to red
+pub fn heat_color(cost: isize, max_cost: isize) -> Rgb {
+    if max_cost <= 0 {
+        return [0, 0, 255];
+    }
+
+    let t = cost.clamp(0, max_cost) as f32 / max_cost as f32;
+
+    let (r, g, b) = if t < 0.5 {
+        let t = t * 2.0;
+        (0.0, t, 1.0 - t)
+    } else {
+        let t = (t - 0.5) * 2.0;
+        (t, 1.0 - t, 0.0)
+    };
+
+    [(r * 255.0) as u8, (g * 255.0) as u8, (b * 255.0) as u8]
+}
+
+/// write the frames as a looping gif, every frame has to be the same size
+/// and all of them together can use at most 256 colors
+pub fn write_gif<W: Write>(
+    writer: &mut W,
+    frames: &[Image],
+    delay: u16,
+) -> io::Result<()> {
+    let first = match frames.first() {
+        Some(first) => first,
+        None => {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "no frames to write",
+            ))
+        }
+    };
+
+    // one past the limit is enough for the encoder to refuse them
+    let mut palette: Vec<Rgb> = vec![];
+    let mut seen: HashSet<Rgb> = HashSet::new();
+
+    for pixel in frames.iter().flat_map(|frame| &frame.pixels) {
+        if palette.len() > 256 {
+            break;
+        }
+
+        if seen.insert(*pixel) {
+            palette.push(*pixel);
+        }
+    }
+
+    let mut encoder =
+        GifEncoder::new(writer, first.width, first.height, &palette, delay)?;
+
+    for frame in frames {
+        encoder.frame(frame)?;
+    }
+
+    encoder.finish()?;
+
+    Ok(())
+}
+
+/// a looping gif written one frame at a time, the palette has to be known
+/// up front so nothing but the frame being encoded is kept around
+pub struct GifEncoder<W: Write> {
+    writer: W,
+    width: u16,
+    height: u16,
+    lookup: HashMap<Rgb, u8>,
+    min_code_size: u8,
+    delay: u16,
+}
+
+impl<W: Write> GifEncoder<W> {
+    /// write the header and the color table, `delay` is in hundredths of a
+    /// second per frame
+    pub fn new(
+        mut writer: W,
+        width: usize,
+        height: usize,
+        colors: &[Rgb],
+        delay: u16,
+    ) -> io::Result<Self> {
+        let mut palette: Vec<Rgb> = vec![];
+        let mut lookup: HashMap<Rgb, u8> = HashMap::new();
+
+        for color in colors {
+            if lookup.contains_key(color) {
+                continue;
+            }
+
+            if palette.len() == 256 {
+                return Err(io::Error::new(
+                    io::ErrorKind::InvalidInput,
+                    "frames use more than 256 colors",
+                ));
+            }
+
+            lookup.insert(*color, palette.len() as u8);
+            palette.push(*color);
+        }
+
+        // the color table size is stored as a power of two, at least 2 bits
+        let mut table_bits: u8 = 1;
+        while (1 << table_bits) < palette.len() {
+            table_bits += 1;
+        }
+        palette.resize(1 << table_bits, [0, 0, 0]);
+
+        // the screen size is 16 bits, a bigger one would wrap around
+        let (Ok(gif_width), Ok(gif_height)) =
+            (u16::try_from(width), u16::try_from(height))
+        else {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!(
+                    "a {} by {} gif is bigger than the 65535 by 65535 gifs \
+                     allow",
+                    width, height
+                ),
+            ));
+        };
+
+        writer.write_all(b"GIF89a")?;
+        writer.write_all(&gif_width.to_le_bytes())?;
+        writer.write_all(&gif_height.to_le_bytes())?;
+        // global color table, 8 bits per channel
+        writer.write_all(&[0xf0 | (table_bits - 1), 0, 0])?;
+
+        for color in &palette {
+            writer.write_all(color)?;
+        }
+
+        // netscape extension to loop forever
+        writer.write_all(&[0x21, 0xff, 0x0b])?;
+        writer.write_all(b"NETSCAPE2.0")?;
+        writer.write_all(&[0x03, 0x01, 0x00, 0x00, 0x00])?;
+
+        Ok(Self {
+            writer,
+            width: gif_width,
+            height: gif_height,
+            lookup,
+            min_code_size: table_bits.max(2),
+            delay,
+        })
+    }
+
+    /// encode one frame, it has to be the size given to `new` and only use
+    /// colors from its palette
+    pub fn frame(&mut self, frame: &Image) -> io::Result<()> {
+        if frame.width != self.width as usize
+            || frame.height != self.height as usize
+        {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "frames are not all the same size",
+            ));
+        }
+
+        let indexes = frame
+            .pixels
+            .iter()
+            .map(|pixel| {
+                self.lookup.get(pixel).copied().ok_or_else(|| {
+                    io::Error::new(
+                        io::ErrorKind::InvalidInput,
+                        format!("{:?} is not in the gif palette", pixel),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<u8>>>()?;
+
+        let writer = &mut self.writer;
+
+        // graphic control extension for the delay
+        writer.write_all(&[0x21, 0xf9, 0x04, 0x00])?;
+        writer.write_all(&self.delay.to_le_bytes())?;
+        writer.write_all(&[0x00, 0x00])?;
+
+        writer.write_all(&[0x2c, 0, 0, 0, 0])?;
+        writer.write_all(&self.width.to_le_bytes())?;
+        writer.write_all(&self.height.to_le_bytes())?;
+        writer.write_all(&[0x00])?;
+
+        writer.write_all(&[self.min_code_size])?;
+
+        for block in lzw_encode(&indexes, self.min_code_size).chunks(255) {
+            writer.write_all(&[block.len() as u8])?;
+            writer.write_all(block)?;
+        }
+
+        writer.write_all(&[0x00])
+    }
+
+    /// write the trailer and hand the writer back
+    pub fn finish(mut self) -> io::Result<W> {
+        self.writer.write_all(&[0x3b])?;
+
+        Ok(self.writer)
+    }
+}
+
+// gif flavored lzw, codes are packed least significant bit first
+fn lzw_encode(data: &[u8], min_code_size: u8) -> Vec<u8> {
+    let clear: u16 = 1 << min_code_size;
+    let end = clear + 1;
+
+    let mut out = vec![];
+    let mut bits = BitWriter::default();
+
+    let mut dict: HashMap<(u16, u8), u16> = HashMap::new();
+    let mut code_size = min_code_size + 1;
+    let mut next_code = end + 1;
+
+    bits.write(&mut out, clear, code_size);
+
+    let mut data = data.iter();
+
+    let mut prefix = match data.next() {
+        Some(first) => *first as u16,
+        None => {
+            bits.write(&mut out, end, code_size);
+            bits.flush(&mut out);
+
+            return out;
+        }
+    };
+
+    for byte in data {
+        if let Some(code) = dict.get(&(prefix, *byte)) {
+            prefix = *code;
+            continue;
+        }
+
+        bits.write(&mut out, prefix, code_size);
+
+        if next_code < MAX_GIF_CODES {
+            dict.insert((prefix, *byte), next_code);
+            next_code += 1;
+
+            // the decoder is one code behind so widen once we pass the limit
+            if next_code > (1 << code_size) && code_size < 12 {
+                code_size += 1;
+            }
+        } else {
+            bits.write(&mut out, clear, code_size);
+
+            dict.clear();
+            code_size = min_code_size + 1;
+            next_code = end + 1;
+        }
+
+        prefix = *byte as u16;
+    }
+
+    bits.write(&mut out, prefix, code_size);
+    bits.write(&mut out, end, code_size);
+    bits.flush(&mut out);
+
+    out
+}
+
+#[derive(Default)]
+struct BitWriter {
+    buffer: u32,
+    count: u8,
+}
+
+impl BitWriter {
+    fn write(&mut self, out: &mut Vec<u8>, code: u16, size: u8) {
+        self.buffer |= (code as u32) << self.count;
+        self.count += size;
+
+        while self.count >= 8 {
+            out.push(self.buffer as u8);
+            self.buffer >>= 8;
+            self.count -= 8;
+        }
+    }
+
+    fn flush(&mut self, out: &mut Vec<u8>) {
+        if self.count > 0 {
+            out.push(self.buffer as u8);
+        }
+
+        self.buffer = 0;
+        self.count = 0;
+    }
+}
+
+fn write_chunk<W: Write>(
+    writer: &mut W,
+    kind: &[u8; 4],
+    data: &[u8],
+) -> io::Result<()> {
+    writer.write_all(&(data.len() as u32).to_be_bytes())?;
+    writer.write_all(kind)?;
+    writer.write_all(data)?;
+
+    let mut crc = Crc32::new();
+    crc.update(kind);
+    crc.update(data);
+
+    writer.write_all(&crc.finish().to_be_bytes())
+}
+
+// a zlib stream made of stored deflate blocks
+fn zlib_stored(data: &[u8]) -> Vec<u8> {
+    let blocks = data.len() / MAX_STORED_BLOCK + 1;
+    let mut out = Vec::with_capacity(data.len() + blocks * 5 + 6);
+
+    // deflate with a 32k window and no preset dictionary
+    out.extend_from_slice(&[0x78, 0x01]);
+
+    let mut chunks = data.chunks(MAX_STORED_BLOCK).peekable();
+
+    if chunks.peek().is_none() {
+        out.extend_from_slice(&[1, 0, 0, 0xff, 0xff]);
+    }
+
+    while let Some(chunk) = chunks.next() {
+        let last = chunks.peek().is_none();
+        let len = chunk.len() as u16;
+
+        out.push(last as u8);
+        out.extend_from_slice(&len.to_le_bytes());
+        out.extend_from_slice(&(!len).to_le_bytes());
+        out.extend_from_slice(chunk);
+    }
+
+    out.extend_from_slice(&adler32(data).to_be_bytes());
+
+    out
+}
+
+fn adler32(data: &[u8]) -> u32 {
+    let mut a: u32 = 1;
+    let mut b: u32 = 0;
+
+    for byte in data {
+        a = (a + *byte as u32) % 65_521;
+        b = (b + a) % 65_521;
+    }
+
+    (b << 16) | a
+}
+
+struct Crc32 {
+    table: [u32; 256],
+    value: u32,
+}
+
+impl Crc32 {
+    fn new() -> Self {
+        let mut table = [0; 256];
+
+        for (n, entry) in table.iter_mut().enumerate() {
+            let mut c = n as u32;
+
+            for _ in 0..8 {
+                c = if c & 1 == 1 {
+                    0xedb8_8320 ^ (c >> 1)
+                } else {
+                    c >> 1
+                };
+            }
+
+            *entry = c;
+        }
+
+        Self {
+            table,
+            value: 0xffff_ffff,
+        }
+    }
+
+    fn update(&mut self, data: &[u8]) {
+        for byte in data {
+            let index = ((self.value ^ *byte as u32) & 0xff) as usize;
+            self.value = self.table[index] ^ (self.value >> 8);
+        }
+    }
+
+    fn finish(&self) -> u32 {
+        self.value ^ 0xffff_ffff
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gif_size_fits_in_16_bits() {
+        let fits = Image::new(65_535, 1, FLOOR);
+        let too_wide = Image::new(65_536, 1, FLOOR);
+
+        assert!(write_gif(&mut io::sink(), &[fits], 10).is_ok());
+
+        let err = write_gif(&mut io::sink(), &[too_wide], 10).unwrap_err();
+        assert_eq!(err.kind(), io::ErrorKind::InvalidInput);
+    }
+}
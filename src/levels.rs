@@ -0,0 +1,280 @@
+// multi level maps
+//
+// a stack of same sized `MapData` levels, level 0 at the bottom. stairs and
+// ladders on a level lead to the same spot one level up or down, the stairs
+// pair up `StairsUp` below with `StairsDown` above and ladders connect to
+// ladders
+//
+// a node is a flat index over every level, `pos + level * width * height`,
+// so the searches still work with plain `usize` nodes
+
+use crate::{
+    astar::Heuristic,
+    graph::SearchSpace,
+    map::{Grid, MapData, MapPos, Tile},
+    movement::{MovementProfile, StepCosts},
+    render::{render, RenderOptions},
+    rng::Pcg32,
+};
+
+/// taking the stairs costs this many straight steps
+pub const STAIRS_COST: isize = 2;
+
+/// climbing a ladder costs this many straight steps
+pub const LADDER_COST: isize = 3;
+
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct LayeredMap {
+    pub width: usize,
+    pub height: usize,
+    pub levels: Vec<MapData>,
+}
+
+impl LayeredMap {
+    /// stack levels on top of each other, they all need the same size
+    pub fn new(levels: Vec<MapData>) -> Result<Self, String> {
+        let first = levels.first().ok_or("a map needs at least one level")?;
+        let (width, height) = (first.map_width, first.map_height);
+
+        if levels
+            .iter()
+            .any(|level| level.map_width != width || level.map_height != height)
+        {
+            return Err("every level needs to be the same size".to_string());
+        }
+
+        Ok(Self {
+            width,
+            height,
+            levels,
+        })
+    }
+
+    /// generate `count` levels and join each pair with a couple of stairs
+    /// and a ladder where both levels have floor, fails when the levels do
+    /// not fit the size
+    pub fn generate(
+        width: usize,
+        height: usize,
+        count: usize,
+        seed: u64,
+    ) -> Result<Self, String> {
+        let mut rng = Pcg32::new(seed);
+
+        let mut levels = (0..count as u64)
+            .map(|level| {
+                let mut map_data =
+                    MapData::new(width, height, seed.wrapping_add(level));
+                map_data.gen()?;
+
+                Ok(map_data)
+            })
+            .collect::<Result<Vec<MapData>, String>>()?;
+
+        for below in 0..count.saturating_sub(1) {
+            let mut shared: Vec<MapPos> = (0..width * height)
+                .filter(|pos| {
+                    levels[below].map[*pos] == Tile::Floor
+                        && levels[below + 1].map[*pos] == Tile::Floor
+                })
+                .collect();
+
+            rng.shuffle(&mut shared);
+
+            for (i, pos) in shared.into_iter().take(3).enumerate() {
+                let (lower, upper) = if i < 2 {
+                    (Tile::StairsUp, Tile::StairsDown)
+                } else {
+                    (Tile::Ladder, Tile::Ladder)
+                };
+
+                levels[below].map[pos] = lower;
+                levels[below + 1].map[pos] = upper;
+            }
+        }
+
+        Self::new(levels)
+    }
+
+    pub fn node(&self, pos: MapPos, level: usize) -> usize {
+        pos + level * self.width * self.height
+    }
+
+    /// the position on its level and the level of a node
+    pub fn split(&self, node: usize) -> (MapPos, usize) {
+        let size = self.width * self.height;
+
+        (node % size, node / size)
+    }
+
+    pub fn tile(&self, node: usize) -> Tile {
+        let (pos, level) = self.split(node);
+
+        self.levels[level].map[pos]
+    }
+
+    /// the levels top down, each with the part of the path on it, the `S`
+    /// and `E` on a level are where the path arrives and leaves
+    pub fn render(&self, path: &[usize], options: RenderOptions) -> String {
+        let mut out = String::new();
+
+        for (level, map_data) in self.levels.iter().enumerate().rev() {
+            let on_level: Vec<MapPos> = path
+                .iter()
+                .map(|node| self.split(*node))
+                .filter(|(_, on)| *on == level)
+                .map(|(pos, _)| pos)
+                .collect();
+
+            out.push_str(&format!("level {}\n", level));
+            out.push_str(&render(map_data, &on_level, None, options));
+            out.push('\n');
+        }
+
+        out
+    }
+}
+
+/// a layered map searched across levels
+pub struct LayeredSpace<'a> {
+    map: &'a LayeredMap,
+    costs: StepCosts,
+    heuristic: Heuristic,
+}
+
+impl<'a> LayeredSpace<'a> {
+    pub fn new(map: &'a LayeredMap) -> Self {
+        Self {
+            map,
+            costs: StepCosts::default(),
+            heuristic: Heuristic::default(),
+        }
+    }
+
+    pub fn with_heuristic(mut self, heuristic: Heuristic) -> Self {
+        self.heuristic = heuristic;
+        self
+    }
+
+    pub fn with_costs(mut self, costs: StepCosts) -> Self {
+        self.costs = costs;
+        self
+    }
+
+    pub fn with_profile(mut self, profile: &MovementProfile) -> Self {
+        self.costs = self.costs.with_profile(profile);
+        self
+    }
+
+    // the level changes out of `node`, with what they cost
+    fn climbs(&self, node: usize) -> Vec<(usize, isize)> {
+        let (pos, level) = self.map.split(node);
+        let top = self.map.levels.len() - 1;
+
+        let up = (level < top).then(|| self.map.node(pos, level + 1));
+        let down = (level > 0).then(|| self.map.node(pos, level - 1));
+
+        let links = match self.map.tile(node) {
+            Tile::StairsUp => vec![(up, Tile::StairsDown, STAIRS_COST)],
+            Tile::StairsDown => vec![(down, Tile::StairsUp, STAIRS_COST)],
+            Tile::Ladder => vec![
+                (up, Tile::Ladder, LADDER_COST),
+                (down, Tile::Ladder, LADDER_COST),
+            ],
+            _ => vec![],
+        };
+
+        links
+            .into_iter()
+            .filter_map(|(next, wanted, cost)| {
+                let next =
+                    next.filter(|next| self.map.tile(*next) == wanted)?;
+                let terrain = self.costs.terrain.get(wanted)?;
+
+                Some((next, cost * terrain * self.costs.straight))
+            })
+            .collect()
+    }
+}
+
+impl SearchSpace for LayeredSpace<'_> {
+    type Node = usize;
+
+    fn neighbors(&self, node: usize) -> Vec<(usize, isize)> {
+        let (pos, level) = self.map.split(node);
+
+        let mut neighbors: Vec<(usize, isize)> = self
+            .costs
+            .neighbors(&self.map.levels[level], pos)
+            .into_iter()
+            .map(|(next, cost)| (self.map.node(next, level), cost))
+            .collect();
+
+        neighbors.extend(self.climbs(node));
+
+        neighbors
+    }
+
+    // the flat distance plus the cheapest way to change each level
+    fn heuristic(&self, from: usize, to: usize) -> isize {
+        let (from, from_level) = self.map.split(from);
+        let (to, to_level) = self.map.split(to);
+
+        let level = &self.map.levels[from_level];
+        let flat = self.heuristic.distance(
+            level.coord(from),
+            level.coord(to),
+            &self.costs,
+        );
+        let levels = from_level.abs_diff(to_level) as isize;
+
+        flat + levels * STAIRS_COST.min(LADDER_COST) * self.costs.straight
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::astar::AStar;
+
+    fn level(rows: [&str; 3]) -> MapData {
+        let map = rows
+            .concat()
+            .chars()
+            .map(|glyph| Tile::from_glyph(glyph).unwrap())
+            .collect();
+
+        MapData::from_tiles(6, 3, map)
+    }
+
+    #[test]
+    fn paths_between_levels_take_the_stairs() {
+        let map = LayeredMap::new(vec![
+            level(["######", "#...<#", "######"]),
+            level(["######", "#...>#", "######"]),
+        ])
+        .unwrap();
+
+        let space =
+            LayeredSpace::new(&map).with_profile(&MovementProfile::walker());
+        let (start, end) = (map.node(7, 0), map.node(7, 1));
+
+        let mut astar = AStar::default();
+        let path = astar.run(&space, start, end);
+
+        assert!(path.contains(&map.node(10, 0)));
+        assert!(path.contains(&map.node(10, 1)));
+        // three steps to the stairs, up them and three steps back
+        assert_eq!(astar.cost(end), Some(6 + STAIRS_COST));
+    }
+
+    #[test]
+    fn levels_too_small_to_generate_are_an_error() {
+        assert!(LayeredMap::generate(4, 4, 2, 1).is_err());
+        assert!(LayeredMap::generate(40, 30, 0, 1).is_err());
+
+        let map = LayeredMap::generate(40, 30, 3, 1).unwrap();
+        assert_eq!(map.levels.len(), 3);
+    }
+}
@@ -0,0 +1,140 @@
+// links
+//
+// edges a map has on top of the eight neighbors of every tile, teleporters,
+// one way drops and locked doors between any two tiles. a link can ask for a
+// key, the searches only take it when the agent they plan for holds that key
+
+use std::collections::BTreeMap;
+
+use crate::map::MapPos;
+
+/// the highest key number, keys are bits in `Keys`
+pub const MAX_KEY: u8 = 31;
+
+/// the keys an agent holds
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Keys(u32);
+
+impl Keys {
+    /// every key there is
+    pub fn all() -> Self {
+        Self(u32::MAX)
+    }
+
+    pub fn with(self, key: u8) -> Self {
+        assert!(key <= MAX_KEY, "keys go up to {}", MAX_KEY);
+
+        Self(self.0 | 1 << key)
+    }
+
+    pub fn has(self, key: u8) -> bool {
+        key <= MAX_KEY && self.0 & 1 << key != 0
+    }
+
+    pub fn iter(self) -> impl Iterator<Item = u8> {
+        (0..=MAX_KEY).filter(move |key| self.has(*key))
+    }
+}
+
+/// what the agent brings to a search beyond how it moves
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct AgentState {
+    pub keys: Keys,
+}
+
+/// a one way edge from `from` to `to`
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Link {
+    pub from: MapPos,
+    pub to: MapPos,
+    /// what taking the link costs in straight steps
+    pub cost: isize,
+    /// the key the agent needs to take it
+    pub key: Option<u8>,
+}
+
+impl Link {
+    pub fn allows(&self, agent: &AgentState) -> bool {
+        self.key.is_none_or(|key| agent.keys.has(key))
+    }
+}
+
+/// every link on a map, looked up by the tile they leave from
+#[derive(Clone, PartialEq, Eq, Default, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Links {
+    from: BTreeMap<MapPos, Vec<Link>>,
+}
+
+impl Links {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn add(&mut self, link: Link) {
+        assert!(link.cost >= 0, "links can not cost less than 0");
+
+        self.from.entry(link.from).or_default().push(link);
+    }
+
+    /// a teleporter that works both ways
+    pub fn portal(&mut self, a: MapPos, b: MapPos, cost: isize) {
+        self.both_ways(a, b, cost, None);
+    }
+
+    /// a drop that can not be climbed back up
+    pub fn drop(&mut self, from: MapPos, to: MapPos, cost: isize) {
+        self.add(Link {
+            from,
+            to,
+            cost,
+            key: None,
+        });
+    }
+
+    /// a door between two tiles that only opens with `key`
+    pub fn door(&mut self, a: MapPos, b: MapPos, key: u8) {
+        self.both_ways(a, b, 1, Some(key));
+    }
+
+    fn both_ways(
+        &mut self,
+        a: MapPos,
+        b: MapPos,
+        cost: isize,
+        key: Option<u8>,
+    ) {
+        self.add(Link {
+            from: a,
+            to: b,
+            cost,
+            key,
+        });
+        self.add(Link {
+            from: b,
+            to: a,
+            cost,
+            key,
+        });
+    }
+
+    /// the links leaving `pos`
+    pub fn from(&self, pos: MapPos) -> &[Link] {
+        self.from.get(&pos).map_or(&[], |links| links)
+    }
+
+    pub fn iter(&self) -> impl Iterator<Item = &Link> {
+        self.from.values().flatten()
+    }
+
+    pub fn len(&self) -> usize {
+        self.from.values().map(|links| links.len()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.from.is_empty()
+    }
+}
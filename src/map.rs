@@ -1,10 +1,54 @@
 use std::cmp::{max, min};
 
-use rand::{prelude::*, Rng};
+use crate::{
+    coord::Coord,
+    gen_config::{CorridorShape, GenConfig, RoomShape},
+    graph::{reachable, GridSpace},
+    links::{Link, Links},
+    movement::MovementProfile,
+    rng::Pcg32,
+};
 
 pub type MapPos = usize;
 
-#[derive(Debug)]
+/// the layout the searches walk over, `MapData` and the packed `BitGrid`
+/// both provide it
+pub trait Grid {
+    fn width(&self) -> usize;
+    fn height(&self) -> usize;
+    fn tile(&self, pos: MapPos) -> Tile;
+
+    /// the links leaving `pos` on top of its neighbors, grids without any
+    /// keep the default
+    fn links(&self, _pos: MapPos) -> &[Link] {
+        &[]
+    }
+
+    /// every link on the grid
+    fn all_links(&self) -> Vec<Link> {
+        vec![]
+    }
+
+    fn len(&self) -> usize {
+        self.width() * self.height()
+    }
+
+    fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    fn coord(&self, pos: MapPos) -> Coord {
+        Coord::from_index(pos, self.width())
+    }
+
+    /// the index of `coord`, `None` when it is off the grid
+    fn index(&self, coord: Coord) -> Option<MapPos> {
+        coord.to_index(self.width(), self.height())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MapRect {
     pub x1: usize,
     pub y1: usize,
@@ -29,109 +73,369 @@ impl MapRect {
             && (self.y2 >= other.y1)
     }
 
-    pub fn center(&self) -> (usize, usize) {
-        ((self.x1 + self.x2) / 2, (self.y1 + self.y2) / 2)
+    pub fn center(&self) -> Coord {
+        Coord::new(
+            ((self.x1 + self.x2) / 2) as isize,
+            ((self.y1 + self.y2) / 2) as isize,
+        )
     }
 }
 
-#[derive(Clone, PartialEq, Eq)]
+/// a hallway the generator carved between two rooms, given by their index in
+/// `MapData::rooms`
+#[derive(Debug, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Corridor {
+    pub from: usize,
+    pub to: usize,
+    /// where the hallway turns, it runs straight from each room's center to
+    /// here
+    pub corner: Coord,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub enum Tile {
     Wall,
     Floor,
+    Water,
+    Mud,
+    Road,
+    Door,
+    Lava,
+    /// leads to the same spot on the level above, which should be
+    /// `StairsDown`
+    StairsUp,
+    StairsDown,
+    /// climbs to a ladder on the same spot of the level above or below
+    Ladder,
+}
+
+impl Tile {
+    pub const COUNT: usize = 10;
+
+    pub const ALL: [Tile; Tile::COUNT] = [
+        Tile::Wall,
+        Tile::Floor,
+        Tile::Water,
+        Tile::Mud,
+        Tile::Road,
+        Tile::Door,
+        Tile::Lava,
+        Tile::StairsUp,
+        Tile::StairsDown,
+        Tile::Ladder,
+    ];
+
+    /// the character ascii maps use for the tile
+    pub fn glyph(self) -> char {
+        match self {
+            Tile::Wall => '#',
+            Tile::Floor => '.',
+            Tile::Water => '~',
+            Tile::Mud => ',',
+            Tile::Road => '=',
+            Tile::Door => '+',
+            Tile::Lava => '^',
+            Tile::StairsUp => '<',
+            Tile::StairsDown => '>',
+            Tile::Ladder => 'H',
+        }
+    }
+
+    /// the tile for an ascii map character, `None` for characters that are
+    /// not a tile's glyph
+    pub fn from_glyph(glyph: char) -> Option<Self> {
+        Tile::ALL.into_iter().find(|tile| tile.glyph() == glyph)
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Tile::Wall => "wall",
+            Tile::Floor => "floor",
+            Tile::Water => "water",
+            Tile::Mud => "mud",
+            Tile::Road => "road",
+            Tile::Door => "door",
+            Tile::Lava => "lava",
+            Tile::StairsUp => "stairs up",
+            Tile::StairsDown => "stairs down",
+            Tile::Ladder => "ladder",
+        }
+    }
 }
 
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
 pub struct MapData {
     pub seed: u64,
-    pub max_rooms: usize,
-    pub max_room_size: usize,
-    pub min_room_size: usize,
+    /// what `gen` makes
+    pub config: GenConfig,
     pub map_width: usize,
     pub map_height: usize,
     pub map: Vec<Tile>,
+    /// portals, drops and doors between tiles that are not neighbors
+    pub links: Links,
+    /// the rooms `gen` carved, empty for maps that were not generated
+    pub rooms: Vec<MapRect>,
+    /// the hallways joining `rooms`
+    pub corridors: Vec<Corridor>,
 }
 
 impl MapData {
     pub fn new(map_width: usize, map_height: usize, seed: u64) -> Self {
-        let size: usize = map_width as usize * map_height as usize;
+        let size: usize = map_width * map_height;
 
         let map: Vec<Tile> = vec![Tile::Wall; size];
 
         Self {
             seed,
-            max_rooms: 30,
-            max_room_size: 8,
-            min_room_size: 4,
+            config: GenConfig::default(),
             map_width,
             map_height,
             map,
+            links: Links::new(),
+            rooms: vec![],
+            corridors: vec![],
         }
     }
 
-    pub fn gen(&mut self) {
-        let mut rng = StdRng::seed_from_u64(self.seed);
-
-        let mut rooms: Vec<MapRect> = Vec::new();
-
-        let room_width = rng.gen_range(self.min_room_size..=self.max_room_size);
-        let room_height =
-            rng.gen_range(self.min_room_size..=self.max_room_size);
-
-        let x = rng.gen_range(1..(self.map_width - room_width - 1));
-        let y = rng.gen_range(1..(self.map_height - room_height - 1));
-
-        let room = MapRect::new(x, y, room_width, room_height);
+    /// a map made from tiles that were not generated, like ones loaded from a
+    /// file
+    pub fn from_tiles(
+        map_width: usize,
+        map_height: usize,
+        map: Vec<Tile>,
+    ) -> Self {
+        Self {
+            map,
+            ..Self::new(map_width, map_height, 0)
+        }
+    }
 
-        self.carve_out_room(&room);
+    pub fn with_config(mut self, config: GenConfig) -> Self {
+        self.config = config;
+        self
+    }
 
-        rooms.push(room);
+    /// rooms joined by a minimum spanning tree of hallways plus `loops`
+    /// more, so every room can be reached from every other. fails without
+    /// touching the map when the config does not fit it or the hallways
+    /// leave it split
+    pub fn gen(&mut self) -> Result<(), String> {
+        self.config.validate(self.map_width, self.map_height)?;
+
+        let mut rng = Pcg32::new(self.seed);
+        let config = self.config;
+
+        // carved from solid rock on the side and only kept once it checks
+        // out, so a second call or a failed one leaves nothing behind
+        let mut carved = Self {
+            links: self.links.clone(),
+            ..Self::new(self.map_width, self.map_height, self.seed)
+                .with_config(config)
+        };
 
-        'make_rooms: for _ in 0..self.max_rooms {
+        'make_rooms: for _ in 0..config.max_rooms {
             let room_width =
-                rng.gen_range(self.min_room_size..=self.max_room_size);
+                rng.range(config.min_room_size..=config.max_room_size);
             let room_height =
-                rng.gen_range(self.min_room_size..=self.max_room_size);
+                rng.range(config.min_room_size..=config.max_room_size);
 
-            let x = rng.gen_range(1..(self.map_width - room_width - 1));
-            let y = rng.gen_range(1..(self.map_height - room_height - 1));
+            let x = rng.range(1..(carved.map_width - room_width - 1));
+            let y = rng.range(1..(carved.map_height - room_height - 1));
 
             let room = MapRect::new(x, y, room_width, room_height);
 
-            for r in &rooms {
+            for r in &carved.rooms {
                 if r.intersects(&room) {
                     continue 'make_rooms;
                 }
             }
 
-            self.carve_out_room(&room);
+            let shape = config.room_shape.pick(&mut rng);
+
+            carved.add_room(room, shape);
+        }
+
+        let mut edges = carved.spanning_tree();
+        edges.extend(carved.loop_edges(&mut rng, &edges));
+
+        for (from, to) in edges {
+            carved.join_rooms(&mut rng, from, to);
+        }
+
+        // the hallways should always join everything, a map they do not is
+        // a bug worth hearing about rather than a map to hand out
+        if !carved.is_connected() {
+            return Err(format!(
+                "seed {} made a map that is split apart",
+                carved.seed
+            ));
+        }
+
+        *self = carved;
+
+        Ok(())
+    }
+
+    // hallway length between the centers of two rooms
+    fn room_distance(&self, a: usize, b: usize) -> isize {
+        self.rooms[a].center().manhattan(self.rooms[b].center())
+    }
+
+    // prim's algorithm over the room centers, the hallways that join every
+    // room for the least digging
+    fn spanning_tree(&self) -> Vec<(usize, usize)> {
+        let mut edges = vec![];
+
+        if self.rooms.is_empty() {
+            return edges;
+        }
+
+        let mut joined = vec![false; self.rooms.len()];
+        joined[0] = true;
+
+        // the closest joined room to each room that is not joined yet
+        let mut closest: Vec<(isize, usize)> = (0..self.rooms.len())
+            .map(|room| (self.room_distance(0, room), 0))
+            .collect();
+
+        for _ in 1..self.rooms.len() {
+            let next = (0..self.rooms.len())
+                .filter(|room| !joined[*room])
+                .min_by_key(|room| closest[*room].0)
+                .expect("a room is left to join");
 
-            let last_room = rooms.last().unwrap();
-            self.carve_out_hallway(&mut rng, &room, last_room);
+            joined[next] = true;
+            edges.push((closest[next].1, next));
 
-            rooms.push(room);
+            for room in 0..self.rooms.len() {
+                let distance = self.room_distance(next, room);
+
+                if !joined[room] && distance < closest[room].0 {
+                    closest[room] = (distance, next);
+                }
+            }
         }
+
+        edges
     }
 
-    fn carve_out_room(&mut self, room: &MapRect) {
+    // up to `loops` more hallways, each from a random room to the closest
+    // room it is not already joined to
+    fn loop_edges(
+        &self,
+        rng: &mut Pcg32,
+        tree: &[(usize, usize)],
+    ) -> Vec<(usize, usize)> {
+        let mut edges = vec![];
+
+        if self.rooms.len() < 3 {
+            return edges;
+        }
+
+        let joined = |edges: &[(usize, usize)], a: usize, b: usize| {
+            tree.iter()
+                .chain(edges)
+                .any(|edge| *edge == (a, b) || *edge == (b, a))
+        };
+
+        for _ in 0..self.config.loops {
+            let from = rng.range(0..self.rooms.len());
+
+            let to = (0..self.rooms.len())
+                .filter(|to| *to != from && !joined(&edges, from, *to))
+                .min_by_key(|to| self.room_distance(from, *to));
+
+            if let Some(to) = to {
+                edges.push((from, to));
+            }
+        }
+
+        edges
+    }
+
+    /// whether a walker can get from any tile it can stand on to any other,
+    /// checked with a flood fill from the first one
+    pub fn is_connected(&self) -> bool {
+        let walker = MovementProfile::walker();
+        let space = GridSpace::new(self).with_profile(&walker);
+
+        let mut open =
+            (0..self.map.len()).filter(|pos| walker.can_enter(self.map[*pos]));
+
+        let Some(first) = open.next() else {
+            return true;
+        };
+
+        let reached = reachable(&space, first);
+
+        open.all(|pos| reached.contains(&pos))
+    }
+
+    /// carve out `room` as `shape` and add it to `rooms`, returns its index
+    pub fn add_room(&mut self, room: MapRect, shape: RoomShape) -> usize {
+        self.carve_out_room(&room, shape);
+        self.rooms.push(room);
+
+        self.rooms.len() - 1
+    }
+
+    /// dig a hallway between two of `rooms` and add it to `corridors`
+    pub fn join_rooms(&mut self, rng: &mut Pcg32, from: usize, to: usize) {
+        let corner = self.carve_out_hallway(rng, from, to);
+
+        self.corridors.push(Corridor { from, to, corner });
+    }
+
+    fn carve_out_room(&mut self, room: &MapRect, shape: RoomShape) {
+        // the ellipse through the middle of each edge of the rect
+        let center_x = (room.x1 + room.x2) as f64 / 2.0;
+        let center_y = (room.y1 + room.y2) as f64 / 2.0;
+        let radius_x = (room.x2 - room.x1) as f64 / 2.0 + 0.5;
+        let radius_y = (room.y2 - room.y1) as f64 / 2.0 + 0.5;
+
         for y in room.y1..=room.y2 {
             for x in room.x1..=room.x2 {
+                if shape == RoomShape::Circle {
+                    let dx = (x as f64 - center_x) / radius_x;
+                    let dy = (y as f64 - center_y) / radius_y;
+
+                    if dx * dx + dy * dy > 1.0 {
+                        continue;
+                    }
+                }
+
                 let index = x + (y * self.map_width);
 
-                self.map[index as usize] = Tile::Floor;
+                self.map[index] = Tile::Floor;
             }
         }
     }
 
+    // a hallway between the centers of two rooms, returns where it turns,
+    // straight hallways never do and return the center of `past_room`
     fn carve_out_hallway(
         &mut self,
-        rng: &mut StdRng,
-        room: &MapRect,
-        past_room: &MapRect,
-    ) {
-        let (c_x, c_y) = room.center();
-        let (p_x, p_y) = past_room.center();
+        rng: &mut Pcg32,
+        room: usize,
+        past_room: usize,
+    ) -> Coord {
+        let center = self.rooms[room].center();
+        let past_center = self.rooms[past_room].center();
+
+        if self.config.corridor_shape == CorridorShape::Straight {
+            self.carve_out_line(center, past_center);
+
+            return past_center;
+        }
+
+        let (c_x, c_y) = (center.x as usize, center.y as usize);
+        let (p_x, p_y) = (past_center.x as usize, past_center.y as usize);
 
         // start from either the past room or current room
-        let (sx, sy) = if rng.gen_bool(0.5) {
+        let (sx, sy) = if rng.chance(0.5) {
             (c_x, p_y)
         } else {
             (p_x, c_y)
@@ -141,33 +445,106 @@ impl MapData {
         let max_x = max(p_x, c_x);
 
         for x in min_x..=max_x {
-            let index = x + (sy * self.map_width);
-
-            self.map[index as usize] = Tile::Floor;
+            self.carve_out_brush(x, sy);
         }
 
         let min_y = min(p_y, c_y);
         let max_y = max(p_y, c_y);
 
         for y in min_y..=max_y {
-            let index = sx + (y * self.map_width);
+            self.carve_out_brush(sx, y);
+        }
+
+        Coord::new(sx as isize, sy as isize)
+    }
+
+    // one step across or down at a time, whichever stays closer to the line
+    // from `from` to `to`
+    fn carve_out_line(&mut self, from: Coord, to: Coord) {
+        let delta = to - from;
+        let step_x = Coord::new(delta.x.signum(), 0);
+        let step_y = Coord::new(0, delta.y.signum());
+
+        // how far off the line a point is, scaled by the line's length
+        let off_line = |at: Coord| {
+            let rel = at - from;
+
+            (rel.x * delta.y - rel.y * delta.x).abs()
+        };
 
-            self.map[index as usize] = Tile::Floor;
+        let mut at = from;
+        self.carve_out_brush(at.x as usize, at.y as usize);
+
+        while at != to {
+            at = if at.x == to.x {
+                at + step_y
+            } else if at.y == to.y
+                || off_line(at + step_x) <= off_line(at + step_y)
+            {
+                at + step_x
+            } else {
+                at + step_y
+            };
+
+            self.carve_out_brush(at.x as usize, at.y as usize);
+        }
+    }
+
+    // a square as wide as the hallways around `x`, `y`, kept off the edge
+    // of the map
+    fn carve_out_brush(&mut self, x: usize, y: usize) {
+        let width = self.config.corridor_width.max(1);
+        let (before, after) = ((width - 1) / 2, width / 2);
+
+        let max_x = self.map_width.saturating_sub(2);
+        let max_y = self.map_height.saturating_sub(2);
+
+        for y in y.saturating_sub(before).max(1)..=(y + after).min(max_y) {
+            for x in x.saturating_sub(before).max(1)..=(x + after).min(max_x) {
+                let index = x + (y * self.map_width);
+
+                self.map[index] = Tile::Floor;
+            }
         }
     }
 }
 
+impl Grid for MapData {
+    fn width(&self) -> usize {
+        self.map_width
+    }
+
+    fn height(&self) -> usize {
+        self.map_height
+    }
+
+    fn tile(&self, pos: MapPos) -> Tile {
+        self.map[pos]
+    }
+
+    fn links(&self, pos: MapPos) -> &[Link] {
+        self.links.from(pos)
+    }
+
+    fn all_links(&self) -> Vec<Link> {
+        self.links.iter().copied().collect()
+    }
+}
+
+/// a map from the default config, panics on maps it does not fit
 pub fn get_map(width: usize, height: usize, seed: u64) -> MapData {
     let mut map = MapData::new(width, height, seed);
 
-    map.gen();
+    if let Err(err) = map.gen() {
+        panic!("{}", err);
+    }
 
     map
 }
 
 #[allow(dead_code)]
 pub fn print_map(map: &MapData) {
-    let width = map.map_width as usize;
+    let width = map.map_width;
 
     for (tile, i) in map.map.iter().zip(1..) {
         let num = if *tile == Tile::Wall { 5 } else { 0 };
@@ -181,3 +558,53 @@ pub fn print_map(map: &MapData) {
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn generated_maps_are_connected() {
+        let configs = [
+            GenConfig::default(),
+            GenConfig::default().with_room_shape(RoomShape::Mixed),
+            GenConfig::default()
+                .with_corridor_shape(CorridorShape::Straight)
+                .with_corridor_width(2),
+            GenConfig::default().with_room_size(2, 4).with_loops(0),
+        ];
+
+        for config in configs {
+            for seed in 0..40 {
+                let mut map = MapData::new(48, 32, seed).with_config(config);
+
+                assert_eq!(map.gen(), Ok(()), "{:?}", config);
+                assert!(map.is_connected());
+            }
+        }
+    }
+
+    #[test]
+    fn gen_starts_from_solid_rock() {
+        let once = get_map(48, 32, 5);
+
+        let mut twice = once.clone();
+        twice.gen().unwrap();
+
+        assert_eq!(twice, once);
+    }
+
+    #[test]
+    fn split_maps_are_not_connected() {
+        let map = MapData::from_tiles(
+            5,
+            3,
+            "#####.#.##.#.##"
+                .chars()
+                .map(|glyph| Tile::from_glyph(glyph).unwrap())
+                .collect(),
+        );
+
+        assert!(!map.is_connected());
+    }
+}
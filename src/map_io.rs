@@ -0,0 +1,559 @@
+// map loading and saving
+//
+// plain ascii maps with one character per tile, see `Tile::glyph`, and the
+// moving ai benchmark formats from https://movingai.com/benchmarks/formats.html
+//
+// ascii maps can list links after the tiles, one per line
+//
+//     link <x,y> <x,y> <cost> [key <n>]
+//
+// is a one way link from the first point to the second
+
+use std::{
+    fs,
+    io::{self, BufRead, BufReader, Read, Write},
+    path::Path,
+};
+
+use crate::{
+    compact::{read_compact, write_compact, BitGrid},
+    coord::Coord,
+    links::{Link, Links, MAX_KEY},
+    map::{MapData, Tile},
+};
+
+/// one line of a moving ai `.scen` file
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Scenario {
+    pub bucket: usize,
+    pub map: String,
+    pub map_width: usize,
+    pub map_height: usize,
+    pub start: Coord,
+    pub goal: Coord,
+    pub optimal_length: f64,
+}
+
+fn invalid(line: usize, msg: impl AsRef<str>) -> io::Error {
+    io::Error::new(
+        io::ErrorKind::InvalidData,
+        format!("line {}: {}", line, msg.as_ref()),
+    )
+}
+
+/// read an ascii map, short lines are padded out with walls and characters
+/// that are not a tile glyph are an error, apart from a space for floor the
+/// way the terminal draws it and the moving ai blocked tiles `@`, `O` and `T`
+/// for walls
+pub fn read_ascii<R: Read>(reader: R) -> io::Result<MapData> {
+    let mut rows: Vec<Vec<Tile>> = vec![];
+    let mut links = vec![];
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+
+        if let Some(link) = line.strip_prefix("link ") {
+            links.push((i + 1, link.to_string()));
+            continue;
+        }
+
+        let row = line
+            .chars()
+            .enumerate()
+            .map(|(column, glyph)| {
+                match glyph {
+                    ' ' => Some(Tile::Floor),
+                    '@' | 'O' | 'T' => Some(Tile::Wall),
+                    _ => Tile::from_glyph(glyph),
+                }
+                .ok_or_else(|| {
+                    invalid(
+                        i + 1,
+                        format!(
+                            "unknown tile `{}` in column {}",
+                            glyph,
+                            column + 1
+                        ),
+                    )
+                })
+            })
+            .collect::<io::Result<Vec<Tile>>>()?;
+
+        rows.push(row);
+    }
+
+    // trailing blank lines are not part of the map
+    while rows.last().is_some_and(|row| row.is_empty()) {
+        rows.pop();
+    }
+
+    let width = rows.iter().map(|row| row.len()).max().unwrap_or(0);
+    let height = rows.len();
+
+    if width == 0 {
+        return Err(invalid(1, "the map is empty"));
+    }
+
+    let mut map = vec![];
+    for mut row in rows {
+        row.resize(width, Tile::Wall);
+        map.extend(row);
+    }
+
+    let mut map_data = MapData::from_tiles(width, height, map);
+
+    for (line, link) in links {
+        map_data.links.add(parse_link(line, &link, width, height)?);
+    }
+
+    Ok(map_data)
+}
+
+// the part of a link line after `link`
+fn parse_link(
+    line: usize,
+    text: &str,
+    width: usize,
+    height: usize,
+) -> io::Result<Link> {
+    let parts: Vec<&str> = text.split_whitespace().collect();
+
+    let point = |text: &str| {
+        let (x, y) = text
+            .split_once(',')
+            .ok_or_else(|| invalid(line, format!("bad point `{}`", text)))?;
+
+        match (x.parse(), y.parse()) {
+            (Ok(x), Ok(y)) => Coord::new(x, y)
+                .to_index(width, height)
+                .ok_or_else(|| invalid(line, "the link leaves the map")),
+            _ => Err(invalid(line, format!("bad point `{}`", text))),
+        }
+    };
+
+    let (from, to, cost, key) = match parts[..] {
+        [from, to, cost] => (from, to, cost, None),
+        [from, to, cost, "key", key] => (from, to, cost, Some(key)),
+        _ => {
+            return Err(invalid(
+                line,
+                "links are `link <x,y> <x,y> <cost> [key <n>]`",
+            ))
+        }
+    };
+
+    let key = key
+        .map(|key| match key.parse::<u8>() {
+            Ok(key) if key <= MAX_KEY => Ok(key),
+            _ => Err(invalid(line, format!("keys go up to {}", MAX_KEY))),
+        })
+        .transpose()?;
+
+    let cost = match cost.parse() {
+        Ok(cost) if cost >= 0 => cost,
+        Ok(_) => return Err(invalid(line, "links can not cost less than 0")),
+        Err(_) => return Err(invalid(line, "bad link cost")),
+    };
+
+    Ok(Link {
+        from: point(from)?,
+        to: point(to)?,
+        cost,
+        key,
+    })
+}
+
+fn write_links<W: Write>(
+    writer: &mut W,
+    links: &Links,
+    width: usize,
+) -> io::Result<()> {
+    for link in links.iter() {
+        write!(
+            writer,
+            "link {} {} {}",
+            Coord::from_index(link.from, width),
+            Coord::from_index(link.to, width),
+            link.cost
+        )?;
+
+        if let Some(key) = link.key {
+            write!(writer, " key {}", key)?;
+        }
+
+        writeln!(writer)?;
+    }
+
+    Ok(())
+}
+
+pub fn write_ascii<W: Write>(
+    writer: &mut W,
+    map_data: &MapData,
+) -> io::Result<()> {
+    for row in map_data.map.chunks(map_data.map_width) {
+        let line: String = row.iter().map(|tile| tile.glyph()).collect();
+
+        writeln!(writer, "{}", line)?;
+    }
+
+    if !map_data.links.is_empty() {
+        writeln!(writer)?;
+        write_links(writer, &map_data.links, map_data.map_width)?;
+    }
+
+    Ok(())
+}
+
+/// read a moving ai `.map`, `S` is swamp which becomes mud, `W` is water and
+/// anything else that is not `.` or `G` becomes a wall
+pub fn read_movingai<R: Read>(reader: R) -> io::Result<MapData> {
+    let mut lines = BufReader::new(reader).lines().enumerate();
+
+    let mut width = None;
+    let mut height = None;
+    // the last line read, errors past the header point at where it ended
+    let mut line_number = 0;
+
+    for (i, line) in lines.by_ref() {
+        let line = line?;
+        line_number = i + 1;
+
+        let mut parts = line.split_whitespace();
+
+        match (parts.next(), parts.next()) {
+            (Some("type"), _) => {}
+            (Some("height"), Some(value)) => {
+                height = Some(
+                    value.parse().map_err(|_| invalid(i + 1, "bad height"))?,
+                )
+            }
+            (Some("width"), Some(value)) => {
+                width = Some(
+                    value.parse().map_err(|_| invalid(i + 1, "bad width"))?,
+                )
+            }
+            (Some("map"), None) => break,
+            (None, _) => {}
+            _ => return Err(invalid(i + 1, format!("unexpected `{}`", line))),
+        }
+    }
+
+    let (width, height): (usize, usize) = match (width, height) {
+        (Some(width), Some(height)) => (width, height),
+        _ => return Err(invalid(line_number, "missing width or height")),
+    };
+
+    // the header is not trusted with an allocation, the rows are read first
+    let len = match width.checked_mul(height) {
+        Some(0) => return Err(invalid(line_number, "the map is empty")),
+        Some(len) => len,
+        None => return Err(invalid(line_number, "the map is too big")),
+    };
+
+    let mut map = vec![];
+
+    for (i, line) in lines.take(height) {
+        let line = line?;
+        let line = line.trim_end_matches('\r');
+        line_number = i + 1;
+
+        // counted in characters, a multi byte one is still one tile
+        let tiles = line.chars().count();
+
+        if tiles != width {
+            return Err(invalid(
+                line_number,
+                format!("expected {} tiles got {}", width, tiles),
+            ));
+        }
+
+        map.extend(line.chars().map(|c| match c {
+            '.' | 'G' => Tile::Floor,
+            'S' => Tile::Mud,
+            'W' => Tile::Water,
+            _ => Tile::Wall,
+        }));
+    }
+
+    if map.len() != len {
+        return Err(invalid(
+            line_number + 1,
+            format!("expected {} rows", height),
+        ));
+    }
+
+    Ok(MapData::from_tiles(width, height, map))
+}
+
+pub fn write_movingai<W: Write>(
+    writer: &mut W,
+    map_data: &MapData,
+) -> io::Result<()> {
+    writeln!(writer, "type octile")?;
+    writeln!(writer, "height {}", map_data.map_height)?;
+    writeln!(writer, "width {}", map_data.map_width)?;
+    writeln!(writer, "map")?;
+
+    for row in map_data.map.chunks(map_data.map_width) {
+        let line: String = row
+            .iter()
+            .map(|tile| match tile {
+                Tile::Wall | Tile::Lava => '@',
+                Tile::Water => 'W',
+                Tile::Mud => 'S',
+                Tile::Floor | Tile::Road | Tile::Door => '.',
+                Tile::StairsUp | Tile::StairsDown | Tile::Ladder => '.',
+            })
+            .collect();
+
+        writeln!(writer, "{}", line)?;
+    }
+
+    Ok(())
+}
+
+/// read every scenario in a moving ai `.scen` file
+pub fn read_scenarios<R: Read>(reader: R) -> io::Result<Vec<Scenario>> {
+    let mut scenarios = vec![];
+
+    for (i, line) in BufReader::new(reader).lines().enumerate() {
+        let line = line?;
+
+        if line.trim().is_empty() || line.starts_with("version") {
+            continue;
+        }
+
+        let parts: Vec<&str> = line.split('\t').collect();
+
+        if parts.len() != 9 {
+            return Err(invalid(
+                i + 1,
+                format!("expected 9 fields got {}", parts.len()),
+            ));
+        }
+
+        let num = |field: usize| -> io::Result<usize> {
+            parts[field].trim().parse().map_err(|_| {
+                invalid(i + 1, format!("bad number `{}`", parts[field]))
+            })
+        };
+
+        scenarios.push(Scenario {
+            bucket: num(0)?,
+            map: parts[1].to_string(),
+            map_width: num(2)?,
+            map_height: num(3)?,
+            start: Coord::new(num(4)? as isize, num(5)? as isize),
+            goal: Coord::new(num(6)? as isize, num(7)? as isize),
+            optimal_length: parts[8]
+                .trim()
+                .parse()
+                .map_err(|_| invalid(i + 1, "bad optimal length"))?,
+        });
+    }
+
+    Ok(scenarios)
+}
+
+pub fn write_scenarios<W: Write>(
+    writer: &mut W,
+    scenarios: &[Scenario],
+) -> io::Result<()> {
+    writeln!(writer, "version 1")?;
+
+    for scen in scenarios {
+        writeln!(
+            writer,
+            "{}\t{}\t{}\t{}\t{}\t{}\t{}\t{}\t{:.8}",
+            scen.bucket,
+            scen.map,
+            scen.map_width,
+            scen.map_height,
+            scen.start.x,
+            scen.start.y,
+            scen.goal.x,
+            scen.goal.y,
+            scen.optimal_length
+        )?;
+    }
+
+    Ok(())
+}
+
+/// the formats a map can be saved in
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum MapFormat {
+    Ascii,
+    MovingAi,
+    Compact,
+}
+
+impl MapFormat {
+    /// `.map` is moving ai, `.cmap` is the compact binary format and
+    /// anything else is ascii
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("map") => MapFormat::MovingAi,
+            Some("cmap") => MapFormat::Compact,
+            _ => MapFormat::Ascii,
+        }
+    }
+}
+
+/// load a map picking the format from the extension, see
+/// `MapFormat::from_path`
+pub fn load_map<P: AsRef<Path>>(path: P) -> io::Result<MapData> {
+    let path = path.as_ref();
+    let file = fs::File::open(path)?;
+
+    match MapFormat::from_path(path) {
+        MapFormat::MovingAi => read_movingai(file),
+        MapFormat::Compact => {
+            Ok(read_compact(&mut BufReader::new(file))?.to_map())
+        }
+        MapFormat::Ascii => read_ascii(file),
+    }
+}
+
+/// save a map picking the format from the extension, like `load_map`
+pub fn save_map<P: AsRef<Path>>(path: P, map_data: &MapData) -> io::Result<()> {
+    let path = path.as_ref();
+
+    save_map_as(path, map_data, MapFormat::from_path(path))
+}
+
+/// save a map in `format` whatever the extension says
+pub fn save_map_as<P: AsRef<Path>>(
+    path: P,
+    map_data: &MapData,
+    format: MapFormat,
+) -> io::Result<()> {
+    let mut writer = io::BufWriter::new(fs::File::create(path)?);
+
+    match format {
+        MapFormat::MovingAi => write_movingai(&mut writer, map_data)?,
+        MapFormat::Compact => {
+            write_compact(&mut writer, &BitGrid::from_map(map_data), None)?
+        }
+        MapFormat::Ascii => write_ascii(&mut writer, map_data)?,
+    }
+
+    writer.flush()
+}
+
+pub fn load_scenarios<P: AsRef<Path>>(path: P) -> io::Result<Vec<Scenario>> {
+    read_scenarios(fs::File::open(path)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::map::get_map;
+
+    // every tile once plus a generated map's worth of floor and wall
+    fn tiles() -> MapData {
+        let mut map = get_map(24, 16, 7).map;
+
+        map[..Tile::COUNT].copy_from_slice(&Tile::ALL);
+
+        MapData::from_tiles(24, 16, map)
+    }
+
+    #[test]
+    fn ascii_round_trip() {
+        let mut map_data = tiles();
+        map_data.links.portal(30, 200, 3);
+        map_data.links.door(50, 51, 2);
+
+        let mut out = vec![];
+        write_ascii(&mut out, &map_data).unwrap();
+
+        assert_eq!(read_ascii(out.as_slice()).unwrap(), map_data);
+    }
+
+    #[test]
+    fn movingai_round_trip() {
+        let map_data = MapData::from_tiles(24, 16, get_map(24, 16, 7).map);
+
+        let mut out = vec![];
+        write_movingai(&mut out, &map_data).unwrap();
+
+        assert_eq!(read_movingai(out.as_slice()).unwrap(), map_data);
+    }
+
+    #[test]
+    fn movingai_keeps_what_it_can() {
+        let mut out = vec![];
+        write_movingai(&mut out, &tiles()).unwrap();
+
+        let map = read_movingai(out.as_slice()).unwrap().map;
+
+        assert_eq!(
+            map[..Tile::COUNT],
+            [
+                Tile::Wall,
+                Tile::Floor,
+                Tile::Water,
+                Tile::Mud,
+                Tile::Floor,
+                Tile::Floor,
+                Tile::Wall,
+                Tile::Floor,
+                Tile::Floor,
+                Tile::Floor,
+            ]
+        );
+    }
+
+    #[test]
+    fn movingai_header_is_checked() {
+        let huge = "type octile\nheight 4294967296\nwidth 4294967296\nmap\n";
+        let short = "type octile\nheight 3\nwidth 2\nmap\n..\n..\n";
+
+        for text in [huge, short, "type octile\nheight 0\nwidth 5\nmap\n"] {
+            let err = read_movingai(text.as_bytes()).unwrap_err();
+
+            assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        }
+    }
+
+    #[test]
+    fn movingai_errors_point_at_the_line() {
+        let cases = [
+            ("type octile\nheight 3\nwidth 2\nmap\n..\n..\n", "line 7:"),
+            ("type octile\nheight 2\nwidth 2\nmap\n..\n...\n", "line 6:"),
+            ("type octile\nheight 2\nmap\n", "line 3:"),
+        ];
+
+        for (text, line) in cases {
+            let err = read_movingai(text.as_bytes()).unwrap_err();
+
+            assert!(err.to_string().starts_with(line), "{}", err);
+        }
+    }
+
+    #[test]
+    fn movingai_rows_are_counted_in_characters() {
+        let text = "type octile\nheight 1\nwidth 2\nmap\n.é\n";
+
+        let map_data = read_movingai(text.as_bytes()).unwrap();
+
+        assert_eq!(map_data.map, [Tile::Floor, Tile::Wall]);
+    }
+
+    #[test]
+    fn ascii_glyphs_are_checked() {
+        let map_data = read_ascii("#@OT\n# .#\n".as_bytes()).unwrap();
+
+        assert_eq!(&map_data.map[..4], &[Tile::Wall; 4]);
+        assert_eq!(map_data.map[5], Tile::Floor);
+
+        let err = read_ascii("####\n#.?#\n".as_bytes()).unwrap_err();
+
+        assert_eq!(err.kind(), io::ErrorKind::InvalidData);
+        assert!(err.to_string().starts_with("line 2:"), "{}", err);
+    }
+}
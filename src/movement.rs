@@ -0,0 +1,324 @@
+// movement rules
+//
+// how much a step costs and which steps are allowed, shared by the searches
+// so they agree on what a path costs
+
+use crate::{
+    coord::{Coord, Direction},
+    links::{AgentState, Keys},
+    map::{Grid, MapPos, Tile},
+};
+
+/// fixed point scale used by `StepCosts::octile`, a straight step costs this
+/// much and a diagonal step sqrt(2) times it
+pub const OCTILE_SCALE: isize = 1_000_000;
+
+/// what stepping onto each terrain costs as a multiple of the step, `None`
+/// makes the terrain impassable
+///
+/// nothing is cheaper than floor so the heuristics, which count floor steps,
+/// never overestimate
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct TerrainCosts {
+    costs: [Option<isize>; Tile::COUNT],
+}
+
+impl Default for TerrainCosts {
+    fn default() -> Self {
+        // walls can be crossed at a price the way the searches always did
+        Self::new(|tile| match tile {
+            Tile::Wall => Some(100),
+            Tile::Floor | Tile::Road | Tile::Door => Some(1),
+            Tile::StairsUp | Tile::StairsDown | Tile::Ladder => Some(1),
+            Tile::Mud => Some(3),
+            Tile::Water => Some(5),
+            Tile::Lava => None,
+        })
+    }
+}
+
+impl TerrainCosts {
+    pub fn new(cost: impl Fn(Tile) -> Option<isize>) -> Self {
+        Self {
+            costs: Tile::ALL.map(cost),
+        }
+    }
+
+    pub fn get(&self, tile: Tile) -> Option<isize> {
+        self.costs[tile as usize]
+    }
+
+    pub fn with(mut self, tile: Tile, cost: Option<isize>) -> Self {
+        self.costs[tile as usize] = cost;
+        self
+    }
+
+    pub fn blocks(&self, tile: Tile) -> bool {
+        self.get(tile).is_none()
+    }
+}
+
+/// how one kind of unit gets around, every search takes one so the same map
+/// serves all of them
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct MovementProfile {
+    pub terrain: TerrainCosts,
+}
+
+impl Default for MovementProfile {
+    fn default() -> Self {
+        Self::walker()
+    }
+}
+
+impl MovementProfile {
+    pub const NAMES: &'static [&'static str] =
+        &["walker", "boat", "ghost", "tank"];
+
+    /// keeps to the ground, wades through water and stays out of walls and
+    /// lava
+    pub fn walker() -> Self {
+        Self {
+            terrain: TerrainCosts::default()
+                .with(Tile::Wall, None)
+                .with(Tile::Lava, None),
+        }
+    }
+
+    /// only ever on water
+    pub fn boat() -> Self {
+        Self {
+            terrain: TerrainCosts::new(|tile| match tile {
+                Tile::Water => Some(1),
+                _ => None,
+            }),
+        }
+    }
+
+    /// floats over everything at the same pace
+    pub fn ghost() -> Self {
+        Self {
+            terrain: TerrainCosts::new(|_| Some(1)),
+        }
+    }
+
+    /// happiest on roads, goes a long way round mud, can not swim and can
+    /// take stairs but not ladders
+    pub fn tank() -> Self {
+        Self {
+            terrain: TerrainCosts::new(|tile| match tile {
+                Tile::Floor | Tile::Road | Tile::Door => Some(1),
+                Tile::StairsUp | Tile::StairsDown => Some(1),
+                Tile::Mud => Some(20),
+                Tile::Wall | Tile::Water | Tile::Lava | Tile::Ladder => None,
+            }),
+        }
+    }
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "walker" => Some(Self::walker()),
+            "boat" => Some(Self::boat()),
+            "ghost" => Some(Self::ghost()),
+            "tank" => Some(Self::tank()),
+            _ => None,
+        }
+    }
+
+    pub fn can_enter(&self, tile: Tile) -> bool {
+        !self.terrain.blocks(tile)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct StepCosts {
+    pub straight: isize,
+    pub diagonal: isize,
+    /// how much each terrain multiplies the step cost by
+    pub terrain: TerrainCosts,
+    /// allow diagonal steps that squeeze past the corner of a wall
+    pub corner_cutting: bool,
+}
+
+impl Default for StepCosts {
+    fn default() -> Self {
+        Self {
+            straight: 1,
+            diagonal: 1,
+            terrain: TerrainCosts::default(),
+            corner_cutting: true,
+        }
+    }
+}
+
+impl StepCosts {
+    /// the rules the moving ai benchmarks use, walls can not be crossed,
+    /// diagonals cost sqrt(2) and can not cut corners, swamp costs the same
+    /// as floor and water can not be entered
+    pub fn octile() -> Self {
+        Self {
+            straight: OCTILE_SCALE,
+            diagonal: (OCTILE_SCALE as f64 * std::f64::consts::SQRT_2).round()
+                as isize,
+            terrain: TerrainCosts::new(|tile| match tile {
+                Tile::Floor | Tile::Road | Tile::Door | Tile::Mud => Some(1),
+                Tile::StairsUp | Tile::StairsDown | Tile::Ladder => Some(1),
+                Tile::Wall | Tile::Water | Tile::Lava => None,
+            }),
+            corner_cutting: false,
+        }
+    }
+
+    /// the same rules with a different terrain table
+    pub fn with_terrain(self, terrain: TerrainCosts) -> Self {
+        Self { terrain, ..self }
+    }
+
+    /// the same rules moving the way `profile` does
+    pub fn with_profile(self, profile: &MovementProfile) -> Self {
+        self.with_terrain(profile.terrain)
+    }
+
+    /// the scale the costs are in, to turn them back into tiles
+    pub fn unit(self) -> isize {
+        self.straight.max(1)
+    }
+
+    pub fn to_tiles(self, cost: isize) -> f64 {
+        cost as f64 / self.unit() as f64
+    }
+
+    /// the cost of stepping from `pos` in `dir`, `None` when the step
+    /// leaves the map or is not allowed
+    pub fn step<G: Grid + ?Sized>(
+        &self,
+        grid: &G,
+        pos: MapPos,
+        dir: Direction,
+    ) -> Option<(MapPos, isize)> {
+        let here = grid.coord(pos);
+        let next = grid.index(here.step(dir))?;
+
+        let diagonal = dir.is_diagonal();
+
+        if diagonal && !self.corner_cutting {
+            let offset = dir.offset();
+
+            let side_a = grid.index(here + Coord::new(offset.x, 0))?;
+            let side_b = grid.index(here + Coord::new(0, offset.y))?;
+
+            if self.terrain.blocks(grid.tile(side_a))
+                || self.terrain.blocks(grid.tile(side_b))
+            {
+                return None;
+            }
+        }
+
+        let base = if diagonal {
+            self.diagonal
+        } else {
+            self.straight
+        };
+
+        Some((next, base * self.terrain.get(grid.tile(next))?))
+    }
+
+    /// every allowed step from `pos` with its cost
+    pub fn neighbors<G: Grid + ?Sized>(
+        &self,
+        grid: &G,
+        pos: MapPos,
+    ) -> Vec<(MapPos, isize)> {
+        Direction::ALL
+            .into_iter()
+            .filter_map(|dir| self.step(grid, pos, dir))
+            .collect()
+    }
+
+    /// every link out of `pos` the agent can take with its cost, the far
+    /// end has to be terrain the agent can enter
+    pub fn links<G: Grid + ?Sized>(
+        &self,
+        grid: &G,
+        pos: MapPos,
+        agent: &AgentState,
+    ) -> Vec<(MapPos, isize)> {
+        grid.links(pos)
+            .iter()
+            .filter(|link| link.allows(agent))
+            .filter_map(|link| {
+                let terrain = self.terrain.get(grid.tile(link.to))?;
+
+                Some((link.to, link.cost * self.straight * terrain))
+            })
+            .collect()
+    }
+
+    /// what a path costs under these rules, `None` if it takes a step that
+    /// is not allowed, the path is end first like the searches return it
+    ///
+    /// steps along links are taken whatever key they need
+    pub fn path_cost<G: Grid + ?Sized>(
+        &self,
+        grid: &G,
+        path: &[MapPos],
+    ) -> Option<isize> {
+        path.windows(2).try_fold(0, |total, pair| {
+            let offset = grid.coord(pair[0]) - grid.coord(pair[1]);
+
+            let step = Direction::from_offset(offset)
+                .and_then(|dir| self.step(grid, pair[1], dir));
+
+            let (_, cost) = step.or_else(|| {
+                let agent = AgentState { keys: Keys::all() };
+
+                self.links(grid, pair[1], &agent)
+                    .into_iter()
+                    .find(|(next, _)| *next == pair[0])
+            })?;
+
+            Some(total + cost)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{astar::AStar, graph::GridSpace, map::MapData};
+
+    #[test]
+    fn profiles_that_can_not_swim_go_around() {
+        // a river with a way around it at the east end
+        let rows = [
+            "##########",
+            "#........#",
+            "#~~~~~~~.#",
+            "#........#",
+            "##########",
+        ];
+        let map = rows
+            .concat()
+            .chars()
+            .map(|glyph| Tile::from_glyph(glyph).unwrap())
+            .collect();
+        let map_data = MapData::from_tiles(10, 5, map);
+        let (start, end) = (11, 31);
+
+        let path = |profile: &MovementProfile| {
+            let space = GridSpace::new(&map_data).with_profile(profile);
+
+            AStar::default().run(&space, start, end)
+        };
+
+        let wades = path(&MovementProfile::walker());
+        let drives = path(&MovementProfile::tank());
+
+        assert!(wades.iter().any(|pos| map_data.map[*pos] == Tile::Water));
+        assert!(drives.iter().all(|pos| map_data.map[*pos] != Tile::Water));
+        assert!(drives.len() > wades.len());
+    }
+}
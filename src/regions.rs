@@ -0,0 +1,335 @@
+// connected regions
+//
+// every tile a terrain table lets an agent onto gets the label of the region
+// it is in, found with the same flood fill breadth first uses. two tiles in
+// different regions have no path between them, so a search can be turned
+// down before it expands anything
+//
+// the regions are a little more generous than the searches, diagonal steps
+// always count and links join both ways whatever key they need, so a search
+// is only ever turned down when it could not have found a path
+
+use std::collections::HashMap;
+
+use crate::{
+    coord::Direction,
+    map::{Grid, MapData, MapPos},
+    movement::TerrainCosts,
+};
+
+pub type RegionId = usize;
+
+#[derive(Clone, Debug)]
+pub struct Regions {
+    terrain: TerrainCosts,
+    labels: Vec<Option<RegionId>>,
+    /// tiles in each region, regions that were merged away or split up are
+    /// left at zero
+    sizes: Vec<usize>,
+    /// where the links landing on each tile start, the map only indexes
+    /// links by where they start and regions join them both ways
+    incoming: HashMap<MapPos, Vec<MapPos>>,
+}
+
+impl Regions {
+    /// label every region of `map_data` under `terrain`
+    pub fn new(map_data: &MapData, terrain: TerrainCosts) -> Self {
+        let mut incoming: HashMap<MapPos, Vec<MapPos>> = HashMap::new();
+
+        for link in map_data.links.iter() {
+            incoming.entry(link.to).or_default().push(link.from);
+        }
+
+        let mut regions = Self {
+            terrain,
+            labels: vec![None; map_data.len()],
+            sizes: vec![],
+            incoming,
+        };
+
+        for pos in 0..map_data.len() {
+            if regions.labels[pos].is_none() && regions.open(map_data, pos) {
+                let region = regions.new_region();
+
+                regions.fill(map_data, pos, None, region);
+            }
+        }
+
+        regions
+    }
+
+    /// the terrain the regions were labelled under
+    pub fn terrain(&self) -> TerrainCosts {
+        self.terrain
+    }
+
+    /// the region of `pos`, `None` for tiles the terrain blocks
+    pub fn region(&self, pos: MapPos) -> Option<RegionId> {
+        self.labels[pos]
+    }
+
+    pub fn same_region(&self, a: MapPos, b: MapPos) -> bool {
+        self.labels[a].is_some() && self.labels[a] == self.labels[b]
+    }
+
+    /// how many regions there are
+    pub fn count(&self) -> usize {
+        self.sizes.iter().filter(|size| **size > 0).count()
+    }
+
+    /// whether no path can go from `start` to `end`
+    ///
+    /// a search can step off a blocked start onto its neighbors, so for one
+    /// of those the regions around it are checked instead
+    pub fn rules_out(
+        &self,
+        map_data: &MapData,
+        start: MapPos,
+        end: MapPos,
+    ) -> bool {
+        if start == end {
+            return false;
+        }
+
+        let Some(goal) = self.labels[end] else {
+            return true;
+        };
+
+        match self.labels[start] {
+            Some(region) => region != goal,
+            None => !self
+                .joined(map_data, start)
+                .into_iter()
+                .any(|next| self.labels[next] == Some(goal)),
+        }
+    }
+
+    /// bring the labels up to date after the tile at `pos` changed
+    ///
+    /// opening a tile merges the regions around it into the biggest of
+    /// them, closing one fills each piece its region might have split into
+    pub fn update(&mut self, map_data: &MapData, pos: MapPos) {
+        match (self.labels[pos], self.open(map_data, pos)) {
+            (None, true) => self.open_tile(map_data, pos),
+            (Some(region), false) => self.close_tile(map_data, pos, region),
+            _ => {}
+        }
+    }
+
+    fn open_tile(&mut self, map_data: &MapData, pos: MapPos) {
+        let mut around: Vec<(RegionId, MapPos)> = vec![];
+
+        for next in self.joined(map_data, pos) {
+            if let Some(region) = self.labels[next] {
+                if around.iter().all(|(seen, _)| *seen != region) {
+                    around.push((region, next));
+                }
+            }
+        }
+
+        let Some(&(keep, _)) =
+            around.iter().max_by_key(|(region, _)| self.sizes[*region])
+        else {
+            let region = self.new_region();
+
+            self.labels[pos] = Some(region);
+            self.sizes[region] = 1;
+
+            return;
+        };
+
+        self.labels[pos] = Some(keep);
+        self.sizes[keep] += 1;
+
+        for (region, seed) in around {
+            if region != keep {
+                self.fill(map_data, seed, Some(region), keep);
+            }
+        }
+    }
+
+    fn close_tile(
+        &mut self,
+        map_data: &MapData,
+        pos: MapPos,
+        region: RegionId,
+    ) {
+        self.labels[pos] = None;
+        self.sizes[region] -= 1;
+
+        // every piece left gets a fresh label, the first fill often takes
+        // the whole region and there is nothing else to do
+        for next in self.joined(map_data, pos) {
+            if self.labels[next] == Some(region) {
+                let piece = self.new_region();
+
+                self.fill(map_data, next, Some(region), piece);
+            }
+        }
+    }
+
+    fn new_region(&mut self) -> RegionId {
+        self.sizes.push(0);
+
+        self.sizes.len() - 1
+    }
+
+    // relabel the tiles labelled `from` that can be reached from `start` as
+    // `to`
+    fn fill(
+        &mut self,
+        map_data: &MapData,
+        start: MapPos,
+        from: Option<RegionId>,
+        to: RegionId,
+    ) {
+        let mut frontier = vec![start];
+        self.relabel(start, to);
+
+        while let Some(current) = frontier.pop() {
+            for next in self.joined(map_data, current) {
+                if self.labels[next] == from {
+                    self.relabel(next, to);
+                    frontier.push(next);
+                }
+            }
+        }
+    }
+
+    fn relabel(&mut self, pos: MapPos, to: RegionId) {
+        if let Some(region) = self.labels[pos] {
+            self.sizes[region] -= 1;
+        }
+
+        self.labels[pos] = Some(to);
+        self.sizes[to] += 1;
+    }
+
+    fn open(&self, map_data: &MapData, pos: MapPos) -> bool {
+        !self.terrain.blocks(map_data.map[pos])
+    }
+
+    // the open tiles one step or one link either way from `pos`
+    fn joined(&self, map_data: &MapData, pos: MapPos) -> Vec<MapPos> {
+        let here = map_data.coord(pos);
+
+        let steps = Direction::ALL
+            .into_iter()
+            .filter_map(|dir| map_data.index(here.step(dir)));
+
+        let outgoing = map_data.links.from(pos).iter().map(|link| link.to);
+        let incoming = self.incoming.get(&pos).into_iter().flatten().copied();
+
+        steps
+            .chain(outgoing)
+            .chain(incoming)
+            .filter(|next| self.open(map_data, *next))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{map::Tile, movement::StepCosts};
+
+    // two rooms with a wall between them, `x` is the wall column
+    fn two_rooms() -> (MapData, impl Fn(usize, usize) -> MapPos) {
+        let (width, height) = (11, 5);
+        let map = (0..width * height)
+            .map(|pos| {
+                let (x, y) = (pos % width, pos / width);
+
+                if x == 0 || y == 0 || x == 5 || x == width - 1 || y == 4 {
+                    Tile::Wall
+                } else {
+                    Tile::Floor
+                }
+            })
+            .collect();
+
+        (MapData::from_tiles(width, height, map), move |x, y| {
+            x + y * width
+        })
+    }
+
+    fn terrain() -> TerrainCosts {
+        StepCosts::octile().terrain
+    }
+
+    // the labels can differ, which tiles share a region can not
+    fn assert_matches_fresh(regions: &Regions, map_data: &MapData) {
+        let fresh = Regions::new(map_data, regions.terrain());
+
+        assert_eq!(regions.count(), fresh.count());
+
+        for a in 0..map_data.len() {
+            assert_eq!(regions.region(a).is_some(), fresh.region(a).is_some());
+
+            for b in 0..map_data.len() {
+                assert_eq!(
+                    regions.same_region(a, b),
+                    fresh.same_region(a, b),
+                    "{} and {}",
+                    a,
+                    b
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn opening_a_wall_joins_regions() {
+        let (mut map_data, index) = two_rooms();
+        let mut regions = Regions::new(&map_data, terrain());
+
+        assert_eq!(regions.count(), 2);
+        assert!(!regions.same_region(index(1, 1), index(9, 3)));
+
+        map_data.map[index(5, 2)] = Tile::Floor;
+        regions.update(&map_data, index(5, 2));
+
+        assert_eq!(regions.count(), 1);
+        assert!(regions.same_region(index(1, 1), index(9, 3)));
+        assert_matches_fresh(&regions, &map_data);
+    }
+
+    #[test]
+    fn closing_a_chokepoint_splits_a_region() {
+        let (mut map_data, index) = two_rooms();
+        map_data.map[index(5, 2)] = Tile::Floor;
+
+        let mut regions = Regions::new(&map_data, terrain());
+        assert_eq!(regions.count(), 1);
+
+        map_data.map[index(5, 2)] = Tile::Wall;
+        regions.update(&map_data, index(5, 2));
+
+        assert_eq!(regions.count(), 2);
+        assert!(!regions.same_region(index(1, 1), index(9, 3)));
+        assert_matches_fresh(&regions, &map_data);
+    }
+
+    #[test]
+    fn drops_join_both_ways() {
+        let (mut map_data, index) = two_rooms();
+        map_data.links.drop(index(4, 2), index(6, 2), 1);
+
+        let mut regions = Regions::new(&map_data, terrain());
+        assert_eq!(regions.count(), 1);
+
+        // closing where the drop lands cuts the rooms apart, opening it
+        // again finds the left room through the incoming side of the link
+        map_data.map[index(6, 2)] = Tile::Wall;
+        regions.update(&map_data, index(6, 2));
+
+        assert_eq!(regions.count(), 2);
+        assert_matches_fresh(&regions, &map_data);
+
+        map_data.map[index(6, 2)] = Tile::Floor;
+        regions.update(&map_data, index(6, 2));
+
+        assert_eq!(regions.count(), 1);
+        assert_matches_fresh(&regions, &map_data);
+    }
+}
@@ -0,0 +1,278 @@
+// ascii renderer
+//
+// draws the map with whatever a search left behind on top of it, the layers
+// are drawn in order so the path always wins over the frontier and the
+// frontier wins over the closed set
+
+use std::{
+    collections::{HashMap, HashSet},
+    fmt::Write,
+};
+
+use crate::map::{MapData, MapPos, Tile};
+
+// blue to red, the 256 color codes
+const HEAT_RAMP: &[u8] = &[
+    17, 18, 19, 20, 21, 27, 33, 39, 45, 51, 50, 49, 48, 47, 46, 82, 118, 154,
+    190, 226, 220, 214, 208, 202, 196,
+];
+
+const RESET: &str = "\x1b[0m";
+
+/// the parts of a search worth looking at after it ran
+#[derive(Default, Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchTrace {
+    /// tiles that were popped off the frontier
+    pub closed: HashSet<MapPos>,
+    /// tiles still waiting on the frontier when the search stopped
+    pub open: Vec<MapPos>,
+    /// the best known cost to reach each tile
+    pub cost: HashMap<MapPos, isize>,
+}
+
+impl SearchTrace {
+    /// the highest cost in the trace, used to scale the heatmaps
+    pub fn max_cost(&self) -> isize {
+        self.cost.values().copied().max().unwrap_or(0)
+    }
+}
+
+#[derive(Clone, Copy)]
+pub struct RenderOptions {
+    pub color: bool,
+    pub closed: bool,
+    pub open: bool,
+    pub heat: bool,
+}
+
+impl Default for RenderOptions {
+    fn default() -> Self {
+        Self {
+            color: true,
+            closed: true,
+            open: true,
+            heat: false,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Cell {
+    Terrain(Tile),
+    Closed,
+    Open,
+    Path,
+    Start,
+    End,
+}
+
+impl Cell {
+    fn glyph(self) -> char {
+        match self {
+            Cell::Terrain(Tile::Floor) => ' ',
+            // `+` is taken by the path
+            Cell::Terrain(Tile::Door) => '\'',
+            Cell::Terrain(tile) => tile.glyph(),
+            Cell::Closed => '.',
+            Cell::Open => 'o',
+            Cell::Path => '+',
+            Cell::Start => 'S',
+            Cell::End => 'E',
+        }
+    }
+
+    fn color(self) -> Option<u8> {
+        match self {
+            Cell::Terrain(Tile::Water) => Some(33),
+            Cell::Terrain(Tile::Mud) => Some(94),
+            Cell::Terrain(Tile::Road) => Some(245),
+            Cell::Terrain(Tile::Door) => Some(130),
+            Cell::Terrain(Tile::Lava) => Some(202),
+            Cell::Terrain(Tile::StairsUp)
+            | Cell::Terrain(Tile::StairsDown)
+            | Cell::Terrain(Tile::Ladder) => Some(226),
+            Cell::Terrain(_) => None,
+            Cell::Closed => Some(25),
+            Cell::Open => Some(178),
+            Cell::Path => Some(40),
+            Cell::Start | Cell::End => Some(196),
+        }
+    }
+}
+
+/// map a cost onto the heat ramp
+pub fn heat_index(cost: isize, max_cost: isize) -> usize {
+    if max_cost <= 0 {
+        return 0;
+    }
+
+    let scaled = cost.clamp(0, max_cost) * (HEAT_RAMP.len() as isize - 1);
+
+    (scaled / max_cost) as usize
+}
+
+/// the rectangle of tiles to draw, in map coordinates
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub struct View {
+    pub x: usize,
+    pub y: usize,
+    pub width: usize,
+    pub height: usize,
+}
+
+impl View {
+    /// the whole map
+    pub fn full(map_data: &MapData) -> Self {
+        Self {
+            x: 0,
+            y: 0,
+            width: map_data.map_width,
+            height: map_data.map_height,
+        }
+    }
+
+    /// a view at most `width` by `height` with `center` as close to its
+    /// middle as the map edges allow
+    pub fn around(
+        map_data: &MapData,
+        center: (usize, usize),
+        width: usize,
+        height: usize,
+    ) -> Self {
+        let width = width.clamp(1, map_data.map_width);
+        let height = height.clamp(1, map_data.map_height);
+
+        Self {
+            x: center
+                .0
+                .saturating_sub(width / 2)
+                .min(map_data.map_width - width),
+            y: center
+                .1
+                .saturating_sub(height / 2)
+                .min(map_data.map_height - height),
+            width,
+            height,
+        }
+    }
+}
+
+/// render the map, the path and optionally a trace into a string
+///
+/// the path is expected the way the searches return it, end first and start
+/// last
+pub fn render(
+    map_data: &MapData,
+    path: &[MapPos],
+    trace: Option<&SearchTrace>,
+    options: RenderOptions,
+) -> String {
+    render_view(map_data, path, trace, options, View::full(map_data))
+}
+
+/// render only the tiles inside `view`, for maps bigger than the terminal
+pub fn render_view(
+    map_data: &MapData,
+    path: &[MapPos],
+    trace: Option<&SearchTrace>,
+    options: RenderOptions,
+    view: View,
+) -> String {
+    let mut cells: Vec<Cell> = map_data
+        .map
+        .iter()
+        .map(|tile| Cell::Terrain(*tile))
+        .collect();
+
+    if let Some(trace) = trace {
+        if options.closed {
+            for pos in &trace.closed {
+                cells[*pos] = Cell::Closed;
+            }
+        }
+
+        if options.open {
+            for pos in &trace.open {
+                cells[*pos] = Cell::Open;
+            }
+        }
+    }
+
+    for step in path {
+        cells[*step] = Cell::Path;
+    }
+
+    if let (Some(start), Some(end)) = (path.last(), path.first()) {
+        cells[*start] = Cell::Start;
+        cells[*end] = Cell::End;
+    }
+
+    let heat = trace.filter(|_| options.heat);
+    let max_cost = heat.map(SearchTrace::max_cost).unwrap_or(0);
+
+    let mut out = String::with_capacity(view.width * view.height * 2);
+
+    let rows = view.y..(view.y + view.height).min(map_data.map_height);
+    let columns = view.x..(view.x + view.width).min(map_data.map_width);
+
+    for i in rows
+        .flat_map(|y| columns.clone().map(move |x| y * map_data.map_width + x))
+    {
+        let cell = &cells[i];
+        let heat_color = heat
+            .and_then(|trace| trace.cost.get(&i))
+            .filter(|_| *cell != Cell::Terrain(Tile::Wall))
+            .map(|cost| HEAT_RAMP[heat_index(*cost, max_cost)]);
+
+        match (options.color, heat_color, cell.color()) {
+            (false, _, _) => out.push(cell.glyph()),
+            (true, Some(bg), _) => {
+                let _ =
+                    write!(out, "\x1b[48;5;{}m{}{}", bg, cell.glyph(), RESET);
+            }
+            (true, None, Some(fg)) => {
+                let _ =
+                    write!(out, "\x1b[38;5;{}m{}{}", fg, cell.glyph(), RESET);
+            }
+            (true, None, None) => out.push(cell.glyph()),
+        }
+
+        if i % map_data.map_width + 1 == columns.end {
+            out.push('\n');
+        }
+    }
+
+    out
+}
+
+pub fn print_map_and_path(map_data: &MapData, path: &[MapPos]) {
+    let options = RenderOptions::default();
+
+    print!("{}", render(map_data, path, None, options));
+}
+
+pub fn print_search(
+    map_data: &MapData,
+    path: &[MapPos],
+    trace: &SearchTrace,
+    options: RenderOptions,
+) {
+    print!("{}", render(map_data, path, Some(trace), options));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn view_follows_the_cursor() {
+        let map_data = MapData::new(100, 50, 1);
+
+        let view = View::around(&map_data, (99, 0), 40, 20);
+        assert_eq!((view.x, view.y, view.width, view.height), (60, 0, 40, 20));
+
+        let view = View::around(&map_data, (50, 25), 200, 200);
+        assert_eq!(view, View::full(&map_data));
+    }
+}
@@ -0,0 +1,256 @@
+// search replay
+//
+// the searches can record every expansion they make, the recording can then
+// be turned into a sequence of frames or a gif to see the search play out
+
+use std::{
+    collections::HashSet,
+    fs::{self, File},
+    io::{self, BufWriter, Write},
+    path::Path,
+};
+
+use crate::{
+    image::{self, map_image, GifEncoder, Image, Rgb},
+    map::{MapData, MapPos},
+};
+
+/// the tile being expanded in a frame
+pub const CURRENT: Rgb = [255, 255, 255];
+
+/// one expansion, the node popped off the frontier and the nodes it pushed
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Step<N = MapPos> {
+    pub expanded: N,
+    pub pushed: Vec<N>,
+}
+
+/// a recording over any search space, only grid recordings can be drawn
+#[derive(Clone, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Replay<N = MapPos> {
+    pub steps: Vec<Step<N>>,
+}
+
+impl<N> Default for Replay<N> {
+    fn default() -> Self {
+        Self { steps: vec![] }
+    }
+}
+
+impl<N> Replay<N> {
+    pub fn expand(&mut self, node: N) {
+        self.steps.push(Step {
+            expanded: node,
+            pushed: vec![],
+        });
+    }
+
+    pub fn push(&mut self, node: N) {
+        if let Some(step) = self.steps.last_mut() {
+            step.pushed.push(node);
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.steps.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.steps.is_empty()
+    }
+}
+
+impl Replay {
+    /// hand `frame` an image every `stride` expansions plus a last one with
+    /// the path drawn over the finished search, only one image is kept and
+    /// each step is drawn onto it so long replays stay cheap
+    pub fn play<F>(
+        &self,
+        map_data: &MapData,
+        path: &[MapPos],
+        stride: usize,
+        mut frame: F,
+    ) -> io::Result<usize>
+    where
+        F: FnMut(&Image) -> io::Result<()>,
+    {
+        let stride = stride.max(1);
+        let mut count = 0;
+        let mut current = map_image(map_data);
+        let mut closed = HashSet::new();
+
+        for (i, step) in self.steps.iter().enumerate() {
+            closed.insert(step.expanded);
+
+            for pos in &step.pushed {
+                if !closed.contains(pos) {
+                    current.pixels[*pos] = image::OPEN;
+                }
+            }
+
+            if i % stride == 0 || i + 1 == self.steps.len() {
+                current.pixels[step.expanded] = CURRENT;
+
+                frame(&current)?;
+                count += 1;
+            }
+
+            current.pixels[step.expanded] = image::CLOSED;
+        }
+
+        image::draw_path(&mut current, path, image::PATH_COLORS[0]);
+        frame(&current)?;
+
+        Ok(count + 1)
+    }
+
+    /// every color `play` can draw on this map
+    fn palette(map_data: &MapData) -> Vec<Rgb> {
+        let mut colors: Vec<Rgb> = map_data
+            .map
+            .iter()
+            .map(|tile| image::tile_color(*tile))
+            .collect();
+
+        colors.extend([
+            image::CLOSED,
+            image::OPEN,
+            CURRENT,
+            image::PATH_COLORS[0],
+            image::START,
+            image::END,
+        ]);
+
+        colors.sort_unstable();
+        colors.dedup();
+
+        colors
+    }
+
+    /// write the frames as numbered images into `dir`, `ext` picks the
+    /// format the same way `Image::save` does
+    pub fn save_frames<P: AsRef<Path>>(
+        &self,
+        dir: P,
+        map_data: &MapData,
+        path: &[MapPos],
+        stride: usize,
+        scale: usize,
+        ext: &str,
+    ) -> io::Result<usize> {
+        let dir = dir.as_ref();
+        fs::create_dir_all(dir)?;
+
+        let mut i = 0;
+
+        self.play(map_data, path, stride, |frame| {
+            let name = format!("frame_{:05}.{}", i, ext);
+            i += 1;
+
+            frame.scaled(scale).save(dir.join(name))
+        })
+    }
+
+    /// write the replay as an animated gif, `delay` is in hundredths of a
+    /// second per frame
+    pub fn save_gif<P: AsRef<Path>>(
+        &self,
+        file: P,
+        map_data: &MapData,
+        path: &[MapPos],
+        stride: usize,
+        scale: usize,
+        delay: u16,
+    ) -> io::Result<()> {
+        let scale = scale.max(1);
+        let writer = BufWriter::new(File::create(file)?);
+
+        let mut encoder = GifEncoder::new(
+            writer,
+            map_data.map_width * scale,
+            map_data.map_height * scale,
+            &Self::palette(map_data),
+            delay,
+        )?;
+
+        self.play(map_data, path, stride, |frame| {
+            encoder.frame(&frame.scaled(scale))
+        })?;
+
+        encoder.finish()?.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        astar::AStar,
+        graph::GridSpace,
+        map::{get_map, Grid, Tile},
+    };
+
+    #[test]
+    fn one_frame_per_expansion() {
+        let map_data = get_map(40, 30, 5);
+        let open: Vec<MapPos> = (0..map_data.len())
+            .filter(|pos| map_data.tile(*pos) != Tile::Wall)
+            .collect();
+        let (start, end) = (open[0], open[open.len() - 1]);
+
+        let mut astar = AStar::recording();
+        let path = astar.run(&GridSpace::new(&map_data), start, end);
+        let replay = astar.replay().unwrap();
+
+        assert_eq!(replay.len(), astar.closed().len());
+
+        let mut frames = 0;
+        let count = replay
+            .play(&map_data, &path, 1, |_| {
+                frames += 1;
+                Ok(())
+            })
+            .unwrap();
+
+        // one per expansion and the last one with the path
+        assert_eq!(count, replay.len() + 1);
+        assert_eq!(frames, count);
+    }
+
+    #[test]
+    fn every_frame_fits_the_palette() {
+        let map_data = get_map(30, 20, 2);
+        let open: Vec<MapPos> = (0..map_data.len())
+            .filter(|pos| map_data.tile(*pos) != Tile::Wall)
+            .collect();
+
+        let mut astar = AStar::recording();
+        let path = astar.run(
+            &GridSpace::new(&map_data),
+            open[0],
+            open[open.len() - 1],
+        );
+
+        let replay = astar.replay().unwrap();
+        let mut encoder = GifEncoder::new(
+            vec![],
+            map_data.map_width,
+            map_data.map_height,
+            &Replay::palette(&map_data),
+            4,
+        )
+        .unwrap();
+
+        replay
+            .play(&map_data, &path, 3, |frame| encoder.frame(frame))
+            .unwrap();
+
+        let bytes = encoder.finish().unwrap();
+
+        assert!(bytes.starts_with(b"GIF89a"));
+        assert_eq!(&bytes[6..10], &[30, 0, 20, 0]);
+        assert_eq!(bytes.last(), Some(&0x3b));
+    }
+}
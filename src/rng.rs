@@ -0,0 +1,174 @@
+// seeded random numbers
+//
+// pcg32 (pcg-xsh-rr with 64 bits of state, https://www.pcg-random.org) and
+// the handful of helpers the generators need on top of it. every bit of the
+// stream and of how ranges, shuffles and picks are drawn from it lives here,
+// so a seed makes the same map no matter which version of any crate is
+// around. changing anything in this file changes every saved seed, the
+// golden map tests are there to catch that
+
+use std::ops::{Bound, RangeBounds};
+
+const MULTIPLIER: u64 = 6364136223846793005;
+
+/// the stream `new` uses, the one from the reference implementation
+pub const DEFAULT_STREAM: u64 = 0xda3e39cb94b95bdb;
+
+#[derive(Clone, Debug)]
+pub struct Pcg32 {
+    state: u64,
+    inc: u64,
+}
+
+impl Pcg32 {
+    pub fn new(seed: u64) -> Self {
+        Self::with_stream(seed, DEFAULT_STREAM)
+    }
+
+    /// seeded the way the reference `pcg32_srandom_r` is, two generators
+    /// on different streams never line up
+    pub fn with_stream(seed: u64, stream: u64) -> Self {
+        let mut rng = Self {
+            state: 0,
+            inc: stream << 1 | 1,
+        };
+
+        rng.step();
+        rng.state = rng.state.wrapping_add(seed);
+        rng.step();
+
+        rng
+    }
+
+    fn step(&mut self) {
+        self.state = self.state.wrapping_mul(MULTIPLIER).wrapping_add(self.inc);
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.step();
+
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        let rot = (old >> 59) as u32;
+
+        xorshifted.rotate_right(rot)
+    }
+
+    /// two draws, high half first
+    pub fn next_u64(&mut self) -> u64 {
+        let high = self.next_u32() as u64;
+        let low = self.next_u32() as u64;
+
+        high << 32 | low
+    }
+
+    /// a number in `0..bound` with no bias towards the low end
+    pub fn below(&mut self, bound: usize) -> usize {
+        assert!(bound > 0, "nothing is below 0");
+
+        let bound = bound as u64;
+
+        // draws under this would make the low numbers come up more often
+        let threshold = bound.wrapping_neg() % bound;
+
+        loop {
+            let draw = self.next_u64();
+
+            if draw >= threshold {
+                return (draw % bound) as usize;
+            }
+        }
+    }
+
+    /// a number in `range`, which can not be empty
+    pub fn range(&mut self, range: impl RangeBounds<usize>) -> usize {
+        let start = match range.start_bound() {
+            Bound::Included(start) => *start,
+            Bound::Excluded(start) => start + 1,
+            Bound::Unbounded => 0,
+        };
+
+        let end = match range.end_bound() {
+            Bound::Included(end) => *end,
+            Bound::Excluded(end) => {
+                end.checked_sub(1).expect("the range is empty")
+            }
+            Bound::Unbounded => usize::MAX,
+        };
+
+        assert!(start <= end, "the range is empty");
+
+        match (end - start).checked_add(1) {
+            Some(len) => start + self.below(len),
+            None => self.next_u64() as usize,
+        }
+    }
+
+    /// a number in `0.0..1.0`, 53 random bits so every value is as likely
+    pub fn float(&mut self) -> f64 {
+        (self.next_u64() >> 11) as f64 / (1u64 << 53) as f64
+    }
+
+    /// true `chance` of the time
+    pub fn chance(&mut self, chance: f64) -> bool {
+        self.float() < chance
+    }
+
+    /// fisher yates from the back
+    pub fn shuffle<T>(&mut self, items: &mut [T]) {
+        for i in (1..items.len()).rev() {
+            items.swap(i, self.below(i + 1));
+        }
+    }
+
+    pub fn choose<'a, T>(&mut self, items: &'a [T]) -> Option<&'a T> {
+        if items.is_empty() {
+            return None;
+        }
+
+        Some(&items[self.below(items.len())])
+    }
+
+    /// `amount` different items, all of them in random order when there
+    /// are not that many
+    pub fn choose_multiple<T: Copy>(
+        &mut self,
+        items: &[T],
+        amount: usize,
+    ) -> Vec<T> {
+        let mut items = items.to_vec();
+        let amount = amount.min(items.len());
+
+        // a shuffle that stops once the front is picked
+        for i in 0..amount {
+            let pick = i + self.below(items.len() - i);
+
+            items.swap(i, pick);
+        }
+
+        items.truncate(amount);
+
+        items
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // the first outputs of the reference pcg32-demo for seed 42 on stream 54
+    #[test]
+    fn matches_reference() {
+        let mut rng = Pcg32::with_stream(42, 54);
+
+        let draws: Vec<u32> = (0..6).map(|_| rng.next_u32()).collect();
+
+        assert_eq!(
+            draws,
+            [
+                0xa15c02b7, 0x7b47f409, 0xba1d3330, 0x83d2f293, 0xbfa4784b,
+                0xcbed606e
+            ]
+        );
+    }
+}
@@ -0,0 +1,409 @@
+// room graph
+//
+// the rooms a generator carved and how they join up. every tile a walker can
+// stand on is in one area, either one of the map's rooms or a stretch of
+// hallway between them, and two areas are adjacent where their tiles touch
+// or a link runs from one to the other. planning over the areas first picks
+// which rooms a path goes through, the tile search after it then only looks
+// inside those
+//
+// maps without rooms still work, every open region is one big hallway
+
+use std::collections::HashSet;
+
+use crate::{
+    astar::AStar,
+    coord::{Coord, Direction},
+    graph::SearchSpace,
+    map::{Grid, MapData, MapPos, MapRect},
+    movement::MovementProfile,
+};
+
+pub type AreaId = usize;
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+pub enum Area {
+    /// the room at this index in `MapData::rooms`, rooms keep their index as
+    /// their area
+    Room(usize),
+    /// open tiles outside every room that connect to each other
+    Corridor,
+}
+
+/// a tile just outside a room that leads out of it
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub struct Door {
+    pub pos: MapPos,
+    pub room: usize,
+    /// the area on the outside, a hallway or a room right next to it
+    pub outside: AreaId,
+}
+
+#[derive(Clone, Debug)]
+pub struct RoomGraph {
+    areas: Vec<Area>,
+    /// the area of each tile, `None` for tiles a walker can not stand on
+    area_of: Vec<Option<AreaId>>,
+    /// where each area is, its room's center or the middle of a hallway,
+    /// what the costs between areas are measured from
+    centers: Vec<Coord>,
+    adjacent: Vec<Vec<AreaId>>,
+    doors: Vec<Door>,
+}
+
+impl RoomGraph {
+    pub fn new(map_data: &MapData) -> Self {
+        let walker = MovementProfile::walker();
+        let open = |pos: MapPos| walker.can_enter(map_data.map[pos]);
+
+        let mut graph = Self {
+            areas: (0..map_data.rooms.len()).map(Area::Room).collect(),
+            area_of: vec![None; map_data.len()],
+            centers: map_data.rooms.iter().map(MapRect::center).collect(),
+            adjacent: vec![],
+            doors: vec![],
+        };
+
+        for (room, rect) in map_data.rooms.iter().enumerate() {
+            for y in rect.y1..=rect.y2 {
+                for x in rect.x1..=rect.x2 {
+                    let Some(pos) =
+                        map_data.index(Coord::new(x as isize, y as isize))
+                    else {
+                        continue;
+                    };
+
+                    // rooms do not overlap but their rects can touch
+                    if open(pos) && graph.area_of[pos].is_none() {
+                        graph.area_of[pos] = Some(room);
+                    }
+                }
+            }
+        }
+
+        // whatever is left is hallway, flood it a piece at a time
+        for pos in 0..map_data.len() {
+            if !open(pos) || graph.area_of[pos].is_some() {
+                continue;
+            }
+
+            let area = graph.areas.len();
+            graph.areas.push(Area::Corridor);
+
+            let mut frontier = vec![pos];
+            let mut tiles = vec![];
+            graph.area_of[pos] = Some(area);
+
+            while let Some(current) = frontier.pop() {
+                tiles.push(map_data.coord(current));
+
+                for next in steps(map_data, current) {
+                    if open(next) && graph.area_of[next].is_none() {
+                        graph.area_of[next] = Some(area);
+                        frontier.push(next);
+                    }
+                }
+            }
+
+            let sum = tiles.iter().fold(Coord::default(), |sum, c| sum + *c);
+            let count = tiles.len() as isize;
+
+            graph.centers.push(Coord::new(sum.x / count, sum.y / count));
+        }
+
+        graph.adjacent = vec![vec![]; graph.areas.len()];
+
+        // every tile of a room meets the same door from up to three sides
+        let mut doors = HashSet::new();
+
+        for pos in 0..map_data.len() {
+            let Some(area) = graph.area_of[pos] else {
+                continue;
+            };
+
+            for next in steps(map_data, pos) {
+                let Some(outside) = graph.area_of[next] else {
+                    continue;
+                };
+
+                if outside == area {
+                    continue;
+                }
+
+                graph.join(area, outside);
+
+                if let Area::Room(room) = graph.areas[area] {
+                    let door = Door {
+                        pos: next,
+                        room,
+                        outside,
+                    };
+
+                    if doors.insert(door) {
+                        graph.doors.push(door);
+                    }
+                }
+            }
+        }
+
+        for link in map_data.links.iter() {
+            if let (Some(from), Some(to)) =
+                (graph.area_of[link.from], graph.area_of[link.to])
+            {
+                if from != to {
+                    graph.join(from, to);
+                }
+            }
+        }
+
+        graph
+    }
+
+    // a one way edge, areas that touch get one each way since the tile
+    // pass comes by from both sides
+    fn join(&mut self, from: AreaId, to: AreaId) {
+        if !self.adjacent[from].contains(&to) {
+            self.adjacent[from].push(to);
+        }
+    }
+
+    pub fn areas(&self) -> &[Area] {
+        &self.areas
+    }
+
+    /// the area the tile at `pos` is in
+    pub fn area(&self, pos: MapPos) -> Option<AreaId> {
+        self.area_of[pos]
+    }
+
+    /// the areas one step out of `area`
+    pub fn adjacent(&self, area: AreaId) -> &[AreaId] {
+        &self.adjacent[area]
+    }
+
+    pub fn doors(&self) -> &[Door] {
+        &self.doors
+    }
+
+    /// the rooms `room` leads to, either right next to it or at the other
+    /// end of one hallway
+    pub fn room_neighbors(&self, room: usize) -> Vec<usize> {
+        let mut rooms = vec![];
+
+        for area in &self.adjacent[room] {
+            let beyond = match self.areas[*area] {
+                Area::Room(_) => std::slice::from_ref(area),
+                Area::Corridor => &self.adjacent[*area],
+            };
+
+            for next in beyond {
+                if let Area::Room(next) = self.areas[*next] {
+                    if next != room && !rooms.contains(&next) {
+                        rooms.push(next);
+                    }
+                }
+            }
+        }
+
+        rooms
+    }
+
+    /// the space a search between `start` and `end` should keep to, the
+    /// areas on the cheapest route between theirs. the whole of `space`
+    /// when either end is not in an area or no route joins them
+    pub fn within<'a, S: SearchSpace<Node = MapPos>>(
+        &'a self,
+        space: &'a S,
+        start: MapPos,
+        end: MapPos,
+    ) -> Within<'a, S> {
+        let route = match (self.area(start), self.area(end)) {
+            (Some(from), Some(to)) if from == to => vec![from],
+            (Some(from), Some(to)) => AStar::default().run(self, from, to),
+            _ => vec![],
+        };
+
+        Within {
+            graph: self,
+            space,
+            areas: (!route.is_empty()).then(|| route.into_iter().collect()),
+        }
+    }
+}
+
+// the tiles one step away from `pos` in every direction
+fn steps(map_data: &MapData, pos: MapPos) -> impl Iterator<Item = MapPos> + '_ {
+    let here = map_data.coord(pos);
+
+    Direction::ALL
+        .into_iter()
+        .filter_map(move |dir| map_data.index(here.step(dir)))
+}
+
+/// a room level search, moving between areas costs the distance between
+/// their centers
+impl SearchSpace for RoomGraph {
+    type Node = AreaId;
+
+    fn neighbors(&self, area: AreaId) -> Vec<(AreaId, isize)> {
+        self.adjacent[area]
+            .iter()
+            .map(|next| {
+                (*next, self.centers[area].manhattan(self.centers[*next]))
+            })
+            .collect()
+    }
+
+    fn heuristic(&self, from: AreaId, to: AreaId) -> isize {
+        self.centers[from].manhattan(self.centers[to])
+    }
+}
+
+/// a tile space cut down to the areas of a room level route
+pub struct Within<'a, S> {
+    graph: &'a RoomGraph,
+    space: &'a S,
+    /// `None` lets every tile through
+    areas: Option<HashSet<AreaId>>,
+}
+
+impl<S: SearchSpace<Node = MapPos>> Within<'_, S> {
+    /// whether the route was found, without one the whole map is searched
+    pub fn is_routed(&self) -> bool {
+        self.areas.is_some()
+    }
+
+    fn allows(&self, pos: MapPos) -> bool {
+        let Some(areas) = &self.areas else {
+            return true;
+        };
+
+        self.graph
+            .area(pos)
+            .is_some_and(|area| areas.contains(&area))
+    }
+}
+
+impl<S: SearchSpace<Node = MapPos>> SearchSpace for Within<'_, S> {
+    type Node = MapPos;
+
+    fn neighbors(&self, pos: MapPos) -> Vec<(MapPos, isize)> {
+        let mut neighbors = self.space.neighbors(pos);
+
+        neighbors.retain(|(next, _)| self.allows(*next));
+
+        neighbors
+    }
+
+    fn heuristic(&self, from: MapPos, to: MapPos) -> isize {
+        self.space.heuristic(from, to)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        astar::Heuristic,
+        graph::GridSpace,
+        map::{get_map, Tile},
+        movement::StepCosts,
+    };
+
+    fn space(map_data: &MapData) -> GridSpace<'_, MapData> {
+        GridSpace::new(map_data)
+            .with_costs(StepCosts::octile())
+            .with_heuristic(Heuristic::Octile)
+    }
+
+    fn cost<S: SearchSpace<Node = MapPos>>(
+        space: &S,
+        start: MapPos,
+        end: MapPos,
+    ) -> Option<isize> {
+        let mut astar = AStar::default();
+        astar.run(space, start, end);
+
+        astar.cost(end)
+    }
+
+    #[test]
+    fn plans_inside_a_room_match_plain_astar() {
+        for seed in 0..5 {
+            let map_data = get_map(60, 40, seed);
+            let graph = RoomGraph::new(&map_data);
+            let space = space(&map_data);
+
+            for room in 0..map_data.rooms.len() {
+                let tiles: Vec<MapPos> = (0..map_data.len())
+                    .filter(|pos| graph.area(*pos) == Some(room))
+                    .collect();
+                let (start, end) = (tiles[0], tiles[tiles.len() - 1]);
+
+                let within = graph.within(&space, start, end);
+
+                assert!(within.is_routed());
+                assert_eq!(
+                    cost(&within, start, end),
+                    cost(&space, start, end),
+                    "seed {} room {}",
+                    seed,
+                    room
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn plans_between_rooms_are_never_cheaper() {
+        for seed in 0..5 {
+            let map_data = get_map(60, 40, seed);
+            let graph = RoomGraph::new(&map_data);
+            let space = space(&map_data);
+
+            let centers: Vec<MapPos> = map_data
+                .rooms
+                .iter()
+                .filter_map(|room| map_data.index(room.center()))
+                .collect();
+
+            for pair in centers.windows(2) {
+                let (start, end) = (pair[0], pair[1]);
+                let plain = cost(&space, start, end).unwrap();
+
+                // a route the tiles do not follow falls back to a full search
+                let planned =
+                    cost(&graph.within(&space, start, end), start, end)
+                        .unwrap_or(plain);
+
+                assert!(planned >= plain, "seed {}", seed);
+            }
+        }
+    }
+
+    #[test]
+    fn tiles_outside_every_area_search_everything() {
+        let map_data = get_map(60, 40, 3);
+        let graph = RoomGraph::new(&map_data);
+        let space = space(&map_data);
+
+        let wall = (0..map_data.len())
+            .find(|pos| map_data.map[*pos] == Tile::Wall)
+            .unwrap();
+        let open = (0..map_data.len())
+            .find(|pos| graph.area(*pos).is_some())
+            .unwrap();
+
+        assert_eq!(graph.area(wall), None);
+
+        for (start, end) in [(wall, open), (open, wall)] {
+            let within = graph.within(&space, start, end);
+
+            assert!(!within.is_routed());
+
+            for pos in 0..map_data.len() {
+                assert_eq!(within.neighbors(pos), space.neighbors(pos));
+            }
+        }
+    }
+}
@@ -0,0 +1,194 @@
+// start and goal sampling
+//
+// picks random start and goal pairs from a seed so the same seed always gives
+// the same pairs. the goal is only ever picked from what the start can reach,
+// so every pair has a path, and it has to be between the minimum and maximum
+// distance from the start
+
+use std::{hash::Hash, ops::RangeInclusive};
+
+use crate::{
+    graph::{reachable, reaching, SearchSpace},
+    map::{Grid, MapData, MapPos},
+    rng::Pcg32,
+};
+
+/// how many starts are tried before giving up, a start with nothing in range
+/// costs a flood fill of its whole region
+pub const ATTEMPTS: usize = 64;
+
+pub struct Sampler {
+    rng: Pcg32,
+    min_distance: isize,
+    max_distance: isize,
+}
+
+impl Sampler {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            rng: Pcg32::new(seed),
+            min_distance: 0,
+            max_distance: isize::MAX,
+        }
+    }
+
+    /// only pick goals at least `distance` from the start
+    pub fn with_min_distance(mut self, distance: isize) -> Self {
+        self.min_distance = distance;
+        self
+    }
+
+    /// only pick goals at most `distance` from the start
+    pub fn with_max_distance(mut self, distance: isize) -> Self {
+        self.max_distance = distance;
+        self
+    }
+
+    /// a start and a goal out of `candidates` with a path between them,
+    /// `None` when no pair turned up in `ATTEMPTS` starts
+    pub fn pair<S, D>(
+        &mut self,
+        space: &S,
+        candidates: &[S::Node],
+        distance: D,
+    ) -> Option<(S::Node, S::Node)>
+    where
+        S: SearchSpace,
+        D: Fn(S::Node, S::Node) -> isize,
+    {
+        self.pair_between(space, candidates, candidates, distance)
+    }
+
+    /// like `pair` with the start out of `starts` and the goal out of `ends`
+    pub fn pair_between<S, D>(
+        &mut self,
+        space: &S,
+        starts: &[S::Node],
+        ends: &[S::Node],
+        distance: D,
+    ) -> Option<(S::Node, S::Node)>
+    where
+        S: SearchSpace,
+        D: Fn(S::Node, S::Node) -> isize,
+    {
+        let starts = self.rng.choose_multiple(starts, ATTEMPTS);
+
+        starts.into_iter().find_map(|start| {
+            let end = self.goal(space, start, ends, &distance)?;
+
+            Some((start, end))
+        })
+    }
+
+    /// a goal out of `candidates` that `start` can reach
+    pub fn goal<S, D>(
+        &mut self,
+        space: &S,
+        start: S::Node,
+        candidates: &[S::Node],
+        distance: D,
+    ) -> Option<S::Node>
+    where
+        S: SearchSpace,
+        D: Fn(S::Node, S::Node) -> isize,
+    {
+        let reached = reachable(space, start);
+        let range = self.range();
+
+        self.pick(candidates, |end| {
+            reached.contains(&end) && range.contains(&distance(start, end))
+        })
+    }
+
+    /// a start out of `candidates` that can reach `end`
+    ///
+    /// the edges are walked backwards from the goal so a start behind a one
+    /// way link is never picked
+    pub fn start<S, D>(
+        &mut self,
+        space: &S,
+        end: S::Node,
+        candidates: &[S::Node],
+        distance: D,
+    ) -> Option<S::Node>
+    where
+        S: SearchSpace,
+        D: Fn(S::Node, S::Node) -> isize,
+    {
+        let reached = reaching(space, candidates, end);
+        let range = self.range();
+
+        self.pick(candidates, |start| {
+            reached.contains(&start) && range.contains(&distance(start, end))
+        })
+    }
+
+    fn range(&self) -> RangeInclusive<isize> {
+        self.min_distance..=self.max_distance
+    }
+
+    fn pick<N: Copy + Eq + Hash>(
+        &mut self,
+        candidates: &[N],
+        keep: impl Fn(N) -> bool,
+    ) -> Option<N> {
+        let kept: Vec<N> = candidates
+            .iter()
+            .copied()
+            .filter(|node| keep(*node))
+            .collect();
+
+        self.rng.choose(&kept).copied()
+    }
+}
+
+/// the manhattan distance between two tiles of a map, what the samplers for
+/// grids measure with
+pub fn map_distance(
+    map_data: &MapData,
+) -> impl Fn(MapPos, MapPos) -> isize + '_ {
+    move |a, b| map_data.coord(a).manhattan(map_data.coord(b))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{graph::GridSpace, map::Tile, movement::StepCosts};
+
+    #[test]
+    fn start_is_never_behind_a_drop() {
+        // two rooms split by a wall, a drop leads from the left one into the
+        // right one and nothing leads back
+        let (width, height) = (11, 5);
+        let map = (0..width * height)
+            .map(|pos| {
+                let (x, y) = (pos % width, pos / width);
+
+                if x == 0 || y == 0 || x == 5 || x == width - 1 || y == 4 {
+                    Tile::Wall
+                } else {
+                    Tile::Floor
+                }
+            })
+            .collect();
+
+        let mut map_data = MapData::from_tiles(width, height, map);
+        let index = |x, y| x + y * width;
+
+        map_data.links.drop(index(4, 2), index(6, 2), 1);
+
+        let space = GridSpace::new(&map_data).with_costs(StepCosts::octile());
+        let candidates: Vec<MapPos> = (0..map_data.len())
+            .filter(|pos| map_data.map[*pos] != Tile::Wall)
+            .collect();
+        let end = index(1, 1);
+
+        for seed in 0..32 {
+            let start = Sampler::new(seed)
+                .start(&space, end, &candidates, map_distance(&map_data))
+                .unwrap();
+
+            assert!(map_data.coord(start).x < 5, "seed {}", seed);
+        }
+    }
+}
@@ -0,0 +1,281 @@
+// scenario runner
+//
+// runs every query in a moving ai `.scen` file through the searches with the
+// benchmark movement rules and checks the cost against the listed optimal
+// length
+
+use std::{
+    io::{self, Write},
+    time::Duration,
+};
+
+use crate::{
+    astar::Heuristic,
+    links::AgentState,
+    map::{Grid, MapData},
+    map_io::Scenario,
+    movement::StepCosts,
+    regions::Regions,
+    room_graph::RoomGraph,
+    search::{search_in, Algorithm, SearchOptions},
+};
+
+/// how far off a cost can be before it counts as a mismatch, the `.scen`
+/// files list lengths to 8 decimal places
+pub const TOLERANCE: f64 = 1e-4;
+
+/// what a search came back with for one scenario
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Outcome {
+    /// the octile length of the path found
+    Cost(f64),
+    NoPath,
+    /// a path that takes a step the benchmark rules do not allow
+    InvalidPath,
+}
+
+impl Outcome {
+    pub fn cost(self) -> Option<f64> {
+        match self {
+            Outcome::Cost(cost) => Some(cost),
+            Outcome::NoPath | Outcome::InvalidPath => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Outcome::Cost(_) => "path",
+            Outcome::NoPath => "no path",
+            Outcome::InvalidPath => "invalid path",
+        }
+    }
+}
+
+pub struct ScenarioResult {
+    pub index: usize,
+    pub algorithm: Algorithm,
+    pub scenario: Scenario,
+    pub outcome: Outcome,
+    pub expanded: usize,
+    pub elapsed: Duration,
+}
+
+impl ScenarioResult {
+    pub fn is_match(&self) -> bool {
+        match self.outcome {
+            Outcome::Cost(cost) => {
+                (cost - self.scenario.optimal_length).abs() < TOLERANCE
+            }
+            Outcome::NoPath | Outcome::InvalidPath => false,
+        }
+    }
+}
+
+/// totals for one algorithm over a whole run
+pub struct Summary {
+    pub algorithm: Algorithm,
+    pub runs: usize,
+    pub mismatches: usize,
+    pub expanded: usize,
+    pub elapsed: Duration,
+}
+
+/// run the scenarios through each algorithm, scenarios that do not fit the
+/// map are skipped
+pub fn run_scenarios(
+    map_data: &MapData,
+    scenarios: &[Scenario],
+    algorithms: &[Algorithm],
+) -> Vec<ScenarioResult> {
+    let costs = StepCosts::octile();
+
+    let options = SearchOptions {
+        heuristic: Heuristic::Octile,
+        costs: Some(costs),
+        profile: None,
+        agent_size: 1,
+        agent: AgentState::default(),
+        record: false,
+    };
+
+    // scenarios between walled off parts of the map fail without a search
+    let regions = Regions::new(map_data, costs.terrain);
+    let rooms = algorithms
+        .contains(&Algorithm::Rooms)
+        .then(|| RoomGraph::new(map_data));
+
+    let mut results = vec![];
+
+    for (index, scenario) in scenarios.iter().enumerate() {
+        let (Some(start), Some(goal)) = (
+            map_data.index(scenario.start),
+            map_data.index(scenario.goal),
+        ) else {
+            continue;
+        };
+
+        for algorithm in algorithms {
+            let found = search_in(
+                *algorithm,
+                map_data,
+                &regions,
+                rooms.as_ref(),
+                start,
+                goal,
+                &options,
+            );
+
+            let outcome = if found.path.is_empty() {
+                Outcome::NoPath
+            } else {
+                match costs.path_cost(map_data, &found.path) {
+                    Some(cost) => Outcome::Cost(costs.to_tiles(cost)),
+                    None => Outcome::InvalidPath,
+                }
+            };
+
+            results.push(ScenarioResult {
+                index,
+                algorithm: *algorithm,
+                scenario: scenario.clone(),
+                outcome,
+                expanded: found.trace.closed.len(),
+                elapsed: found.elapsed,
+            });
+        }
+    }
+
+    results
+}
+
+pub fn summarize(
+    results: &[ScenarioResult],
+    algorithms: &[Algorithm],
+) -> Vec<Summary> {
+    algorithms
+        .iter()
+        .map(|algorithm| {
+            let mine = results.iter().filter(|r| r.algorithm == *algorithm);
+
+            let mut summary = Summary {
+                algorithm: *algorithm,
+                runs: 0,
+                mismatches: 0,
+                expanded: 0,
+                elapsed: Duration::ZERO,
+            };
+
+            for result in mine {
+                summary.runs += 1;
+                summary.expanded += result.expanded;
+                summary.elapsed += result.elapsed;
+
+                if !result.is_match() {
+                    summary.mismatches += 1;
+                }
+            }
+
+            summary
+        })
+        .collect()
+}
+
+/// every mismatch followed by the per algorithm totals
+pub fn write_table<W: Write>(
+    writer: &mut W,
+    results: &[ScenarioResult],
+    algorithms: &[Algorithm],
+) -> io::Result<()> {
+    let mismatches: Vec<&ScenarioResult> =
+        results.iter().filter(|result| !result.is_match()).collect();
+
+    if !mismatches.is_empty() {
+        writeln!(
+            writer,
+            "{:>6} {:<10} {:>11} {:>11} {:>14} {:>14}",
+            "scen", "algorithm", "start", "goal", "optimal", "found"
+        )?;
+
+        for result in mismatches {
+            let found = match result.outcome {
+                Outcome::Cost(cost) => format!("{:.4}", cost),
+                outcome => outcome.name().to_string(),
+            };
+
+            writeln!(
+                writer,
+                "{:>6} {:<10} {:>11} {:>11} {:>14.4} {:>14}",
+                result.index,
+                result.algorithm.name(),
+                result.scenario.start.to_string(),
+                result.scenario.goal.to_string(),
+                result.scenario.optimal_length,
+                found
+            )?;
+        }
+
+        writeln!(writer)?;
+    }
+
+    writeln!(
+        writer,
+        "{:<10} {:>6} {:>10} {:>12} {:>12}",
+        "algorithm", "runs", "mismatch", "avg expanded", "avg time"
+    )?;
+
+    for summary in summarize(results, algorithms) {
+        let runs = summary.runs.max(1);
+
+        writeln!(
+            writer,
+            "{:<10} {:>6} {:>10} {:>12} {:>12?}",
+            summary.algorithm.name(),
+            summary.runs,
+            summary.mismatches,
+            summary.expanded / runs,
+            summary.elapsed / runs as u32
+        )?;
+    }
+
+    Ok(())
+}
+
+/// one row per scenario and algorithm
+pub fn write_csv<W: Write>(
+    writer: &mut W,
+    results: &[ScenarioResult],
+) -> io::Result<()> {
+    writeln!(
+        writer,
+        "scen,bucket,algorithm,start_x,start_y,goal_x,goal_y,optimal,found,\
+         outcome,match,expanded,micros"
+    )?;
+
+    for result in results {
+        let scen = &result.scenario;
+
+        writeln!(
+            writer,
+            "{},{},{},{},{},{},{},{:.8},{},{},{},{},{}",
+            result.index,
+            scen.bucket,
+            result.algorithm.name(),
+            scen.start.x,
+            scen.start.y,
+            scen.goal.x,
+            scen.goal.y,
+            scen.optimal_length,
+            result
+                .outcome
+                .cost()
+                .map(|cost| format!("{:.8}", cost))
+                .unwrap_or_default(),
+            result.outcome.name(),
+            result.is_match(),
+            result.expanded,
+            result.elapsed.as_micros()
+        )?;
+    }
+
+    Ok(())
+}
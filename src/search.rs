@@ -0,0 +1,338 @@
+// running the searches
+//
+// one entry point over all of the algorithms so the command line, the
+// explorer and the scenario runner do not each need to know how every
+// search wants to be called
+
+use std::{
+    collections::HashMap,
+    time::{Duration, Instant},
+};
+
+use crate::{
+    astar::{AStar, Heuristic},
+    breadth_first_alt::BreadthFirst,
+    clearance::Clearance,
+    dijkstra::{self, Dijkstra},
+    graph::{path_from, GridSpace},
+    links::AgentState,
+    map::{Grid, MapData, MapPos},
+    movement::{MovementProfile, StepCosts, TerrainCosts},
+    regions::Regions,
+    render::SearchTrace,
+    replay::Replay,
+    room_graph::RoomGraph,
+};
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum Algorithm {
+    AStar,
+    Dijkstra,
+    BreadthFirst,
+    /// a* over the rooms first, then a* over the tiles of the rooms and
+    /// hallways on that route
+    Rooms,
+}
+
+impl Algorithm {
+    pub const ALL: &'static [Algorithm] = &[
+        Algorithm::AStar,
+        Algorithm::Dijkstra,
+        Algorithm::BreadthFirst,
+        Algorithm::Rooms,
+    ];
+
+    /// the ones that always find the cheapest path, breadth first only
+    /// counts steps and rooms keeps to the rooms it planned through
+    pub const CHEAPEST: &'static [Algorithm] =
+        &[Algorithm::AStar, Algorithm::Dijkstra];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name {
+            "astar" | "a*" => Some(Algorithm::AStar),
+            "dijkstra" => Some(Algorithm::Dijkstra),
+            "bfs" | "breadth_first" => Some(Algorithm::BreadthFirst),
+            "rooms" | "hierarchical" => Some(Algorithm::Rooms),
+            _ => None,
+        }
+    }
+
+    pub fn name(self) -> &'static str {
+        match self {
+            Algorithm::AStar => "astar",
+            Algorithm::Dijkstra => "dijkstra",
+            Algorithm::BreadthFirst => "bfs",
+            Algorithm::Rooms => "rooms",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SearchOptions {
+    pub heuristic: Heuristic,
+    /// the step costs to search with, each search keeps its own defaults
+    /// when this is `None`
+    pub costs: Option<StepCosts>,
+    /// how the agent moves, replaces the terrain costs of `costs` when set
+    pub profile: Option<MovementProfile>,
+    /// how many tiles across the agent is, paths are for its top left tile
+    /// and only go where the whole agent fits
+    pub agent_size: usize,
+    /// what the agent carries, the keys decide which locked links it can
+    /// take
+    pub agent: AgentState,
+    /// keep a replay of every expansion, breadth first can not record
+    pub record: bool,
+}
+
+impl Default for SearchOptions {
+    fn default() -> Self {
+        Self {
+            heuristic: Heuristic::default(),
+            costs: None,
+            profile: None,
+            agent_size: 1,
+            agent: AgentState::default(),
+            record: false,
+        }
+    }
+}
+
+impl SearchOptions {
+    /// the clearance the searches need, `None` for single tile agents
+    pub fn clearance(&self, map_data: &MapData) -> Option<Clearance> {
+        (self.agent_size > 1).then(|| {
+            Clearance::new(map_data, &self.profile.unwrap_or_default())
+        })
+    }
+
+    /// the step costs under these options, `costs` are what the search
+    /// uses when no costs were given
+    pub fn step_costs(&self, costs: StepCosts) -> StepCosts {
+        let costs = self.costs.unwrap_or(costs);
+
+        match &self.profile {
+            Some(profile) => costs.with_profile(profile),
+            None => costs,
+        }
+    }
+
+    /// the map as a search space under these options, `costs` as for
+    /// `step_costs`
+    pub fn grid_space<'a>(
+        &self,
+        map_data: &'a MapData,
+        costs: StepCosts,
+    ) -> GridSpace<'a, MapData> {
+        let mut space = GridSpace::new(map_data)
+            .with_heuristic(self.heuristic)
+            .with_costs(self.step_costs(costs))
+            .with_agent(self.agent);
+
+        if let Some(clearance) = self.clearance(map_data) {
+            space = space.with_clearance(clearance, self.agent_size);
+        }
+
+        space
+    }
+}
+
+/// everything needed to run a search again later
+#[derive(Clone, Copy, PartialEq, Eq, Debug)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Query {
+    pub algorithm: Algorithm,
+    pub start: MapPos,
+    pub end: MapPos,
+    pub options: SearchOptions,
+}
+
+impl Query {
+    pub fn run(&self, map_data: &MapData) -> Found {
+        search(
+            self.algorithm,
+            map_data,
+            self.start,
+            self.end,
+            &self.options,
+        )
+    }
+}
+
+/// the result of running one of the searches
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Found {
+    pub path: Vec<MapPos>,
+    pub trace: SearchTrace,
+    pub replay: Option<Replay>,
+    pub elapsed: Duration,
+}
+
+/// the terrain `algorithm` moves over under `options`, what its regions
+/// have to be labelled with
+pub fn search_terrain(
+    algorithm: Algorithm,
+    options: &SearchOptions,
+) -> TerrainCosts {
+    match algorithm {
+        Algorithm::AStar | Algorithm::Rooms => {
+            options.step_costs(StepCosts::default()).terrain
+        }
+        Algorithm::Dijkstra => {
+            options.step_costs(dijkstra::grid_costs()).terrain
+        }
+        Algorithm::BreadthFirst => options.profile.unwrap_or_default().terrain,
+    }
+}
+
+/// `search` that asks `regions` first and gives up on the spot when start
+/// and end are not connected, regions for some other terrain are ignored.
+/// `rooms` is the room graph of `map_data` for `Algorithm::Rooms` to plan
+/// over, without one it builds its own every search
+pub fn search_in(
+    algorithm: Algorithm,
+    map_data: &MapData,
+    regions: &Regions,
+    rooms: Option<&RoomGraph>,
+    start: MapPos,
+    end: MapPos,
+    options: &SearchOptions,
+) -> Found {
+    let now = Instant::now();
+
+    if regions.terrain() == search_terrain(algorithm, options)
+        && regions.rules_out(map_data, start, end)
+    {
+        return Found {
+            path: vec![],
+            trace: SearchTrace::default(),
+            replay: None,
+            elapsed: now.elapsed(),
+        };
+    }
+
+    run(algorithm, map_data, rooms, start, end, options)
+}
+
+/// run one of the searches and collect everything the output needs
+pub fn search(
+    algorithm: Algorithm,
+    map_data: &MapData,
+    start: MapPos,
+    end: MapPos,
+    options: &SearchOptions,
+) -> Found {
+    run(algorithm, map_data, None, start, end, options)
+}
+
+fn run(
+    algorithm: Algorithm,
+    map_data: &MapData,
+    rooms: Option<&RoomGraph>,
+    start: MapPos,
+    end: MapPos,
+    options: &SearchOptions,
+) -> Found {
+    let now = Instant::now();
+
+    let new_astar = || {
+        if options.record {
+            AStar::recording()
+        } else {
+            AStar::default()
+        }
+    };
+
+    match algorithm {
+        Algorithm::AStar => {
+            let mut astar = new_astar();
+
+            let space = options.grid_space(map_data, StepCosts::default());
+            let path = astar.run(&space, start, end);
+
+            Found {
+                path,
+                elapsed: now.elapsed(),
+                trace: astar.trace(),
+                replay: astar.replay().cloned(),
+            }
+        }
+        Algorithm::Rooms => {
+            let mut astar = new_astar();
+
+            let built;
+            let graph = match rooms {
+                Some(graph) => graph,
+                None => {
+                    built = RoomGraph::new(map_data);
+                    &built
+                }
+            };
+
+            let space = options.grid_space(map_data, StepCosts::default());
+            let within = graph.within(&space, start, end);
+            let mut path = astar.run(&within, start, end);
+
+            // the route is planned for a walker, an agent too big or
+            // without the keys can find it closed
+            if path.is_empty() && within.is_routed() {
+                astar = new_astar();
+                path = astar.run(&space, start, end);
+            }
+
+            Found {
+                path,
+                elapsed: now.elapsed(),
+                trace: astar.trace(),
+                replay: astar.replay().cloned(),
+            }
+        }
+        Algorithm::Dijkstra => {
+            let mut dijkstra = if options.record {
+                Dijkstra::recording()
+            } else {
+                Dijkstra::new()
+            };
+
+            let space = options.grid_space(map_data, dijkstra::grid_costs());
+            let came_from = dijkstra.run(&space, start, end);
+            let path = path_from(&came_from, start, end);
+
+            Found {
+                path,
+                elapsed: now.elapsed(),
+                trace: dijkstra.trace(),
+                replay: dijkstra.replay().cloned(),
+            }
+        }
+        Algorithm::BreadthFirst => {
+            let mut breadth_first = BreadthFirst::new()
+                .with_profile(&options.profile.unwrap_or_default())
+                .with_agent(options.agent);
+
+            if let Some(clearance) = options.clearance(map_data) {
+                breadth_first =
+                    breadth_first.with_clearance(clearance, options.agent_size);
+            }
+            let came_from: HashMap<MapPos, MapPos> = breadth_first
+                .run(map_data, map_data.coord(start))
+                .into_iter()
+                .filter_map(|(pos, prev)| {
+                    let index = |pos| map_data.index(pos);
+
+                    Some((index(pos)?, index(prev?)?))
+                })
+                .collect();
+            let path = path_from(&came_from, start, end);
+
+            Found {
+                path,
+                elapsed: now.elapsed(),
+                trace: breadth_first.trace(map_data),
+                replay: None,
+            }
+        }
+    }
+}
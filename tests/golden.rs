@@ -0,0 +1,123 @@
+// golden maps
+//
+// generated maps checked tile for tile against ones saved in tests/golden,
+// so a seed keeps making the same map. the maps are compared as saved ascii
+// map files, not as `gen` draws them, so a change to the terminal output
+// does not look like the seeds drifting. a change that is meant to alter
+// the maps can write new ones with `UPDATE_GOLDEN=1 cargo test`
+
+use std::{env, fs, path::Path, process::Command};
+
+const CASES: &[(&str, &[&str])] = &[
+    ("rooms-1", &["--seed", "1"]),
+    ("rooms-default", &["--width", "60", "--height", "60"]),
+    (
+        "rooms-circle-straight",
+        &[
+            "--seed",
+            "7",
+            "--room-shape",
+            "circle",
+            "--corridor",
+            "straight",
+            "--corridor-width",
+            "2",
+        ],
+    ),
+    ("rooms-mixed", &["--seed", "8", "--room-shape", "mixed"]),
+    ("bsp-1", &["--seed", "1", "--generator", "bsp"]),
+    ("caves-1", &["--seed", "1", "--generator", "caves"]),
+    ("drunkard-1", &["--seed", "1", "--generator", "drunkard"]),
+    (
+        "backtracker-1",
+        &["--seed", "1", "--generator", "backtracker"],
+    ),
+    ("prim-1", &["--seed", "1", "--generator", "prim"]),
+    ("kruskal-1", &["--seed", "1", "--generator", "kruskal"]),
+    ("terrain-1", &["--seed", "1", "--generator", "terrain"]),
+];
+
+fn generate(args: &[&str]) -> String {
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR"))
+        .join(format!("golden-{}.txt", args.join("_")));
+
+    let output = Command::new(env!("CARGO_BIN_EXE_path_finding"))
+        .args(["gen", "--width", "48", "--height", "24"])
+        .args(args)
+        .arg("--out")
+        .arg(&out)
+        .args(["--format", "ascii"])
+        .output()
+        .expect("the binary runs");
+
+    assert!(
+        output.status.success(),
+        "gen {:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let map = fs::read_to_string(&out).expect("gen saved the map");
+    fs::remove_file(&out).expect("the saved map can be removed");
+
+    map
+}
+
+// the first tile that differs, `None` when the maps match
+fn first_difference(map: &str, golden: &str) -> Option<(usize, usize)> {
+    let rows = map.lines().zip(golden.lines());
+
+    for (y, (row, golden_row)) in rows.enumerate() {
+        let mut tiles = row.chars().zip(golden_row.chars());
+
+        if let Some(x) = tiles.position(|(tile, golden)| tile != golden) {
+            return Some((x, y));
+        }
+
+        if row.len() != golden_row.len() {
+            return Some((row.len().min(golden_row.len()), y));
+        }
+    }
+
+    let (rows, golden_rows) = (map.lines().count(), golden.lines().count());
+
+    (rows != golden_rows).then_some((0, rows.min(golden_rows)))
+}
+
+#[test]
+fn maps_match_golden() {
+    let dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/golden");
+    let update = env::var_os("UPDATE_GOLDEN").is_some();
+
+    let mut drifted = vec![];
+
+    for (name, args) in CASES {
+        let map = generate(args);
+        let file = dir.join(format!("{}.txt", name));
+
+        if update {
+            fs::write(&file, &map).expect("golden maps can be written");
+            continue;
+        }
+
+        let golden = fs::read_to_string(&file)
+            .unwrap_or_else(|_| panic!("no golden map {}", file.display()));
+
+        if let Some((x, y)) = first_difference(&map, &golden) {
+            drifted.push(format!("{} at {},{}", name, x, y));
+        }
+    }
+
+    assert!(
+        drifted.is_empty(),
+        "maps drifted from golden: {:?}",
+        drifted
+    );
+}
+
+#[test]
+fn same_seed_same_map() {
+    for (_, args) in CASES {
+        assert_eq!(generate(args), generate(args), "gen {:?}", args);
+    }
+}
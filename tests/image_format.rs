@@ -0,0 +1,51 @@
+// image formats
+//
+// --format decides how an image is written, the extension of --out only
+// counts when the format was left out
+
+use std::{fs, path::Path, process::Command};
+
+const PNG: &[u8] = b"\x89PNG";
+const PPM: &[u8] = b"P6";
+
+fn write_image(name: &str, args: &[&str]) -> Vec<u8> {
+    let out = Path::new(env!("CARGO_TARGET_TMPDIR")).join(name);
+
+    let output = Command::new(env!("CARGO_BIN_EXE_path_finding"))
+        .args(args)
+        .args(["--width", "32", "--height", "24", "--seed", "3"])
+        .arg("--out")
+        .arg(&out)
+        .output()
+        .expect("the binary runs");
+
+    assert!(
+        output.status.success(),
+        "{:?} failed: {}",
+        args,
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let bytes = fs::read(&out).expect("the image was saved");
+    fs::remove_file(&out).expect("the saved image can be removed");
+
+    bytes
+}
+
+#[test]
+fn format_wins_over_extension() {
+    let png = write_image("format-png", &["gen", "--format", "png"]);
+    assert!(png.starts_with(PNG));
+
+    let ppm = write_image("format-ppm.png", &["find", "--format", "ppm"]);
+    assert!(ppm.starts_with(PPM));
+}
+
+#[test]
+fn extension_picks_missing_format() {
+    let png = write_image("extension.png", &["gen"]);
+    assert!(png.starts_with(PNG));
+
+    let ppm = write_image("extension.ppm", &["find"]);
+    assert!(ppm.starts_with(PPM));
+}